@@ -1,7 +1,6 @@
 use origin_sdk::{
     protocol::{
         game::GetAllGameInfo,
-        profile::GetProfile,
         system::{GetConfig, GetInternetConnectedState},
     },
     sdk::{ClientConfig, OriginSdk, ORIGIN_SDK_PORT},
@@ -24,6 +23,7 @@ async fn main() -> Result<(), Box<dyn Error>> {
         multiplayer_id: "1026480".to_string(),
         title: "Mirror's Edge™ Catalyst".to_string(),
         version_override: None,
+        fetch_profile: true,
     };
 
     // Connect to the Origin SDK server at the given address
@@ -43,8 +43,8 @@ async fn main() -> Result<(), Box<dyn Error>> {
     let state = client.request(GetInternetConnectedState {}).await?;
     info!("Connected to the internet?: {}", state.connected);
 
-    // Fetch the first user profile (index 0 = current user)
-    let profile = client.request(GetProfile { index: 0 }).await?;
+    // Profile of the current user, fetched while connecting
+    let profile = client.current_user().await?;
     info!("Profile: {:#?}", profile);
 
     // Fetch Service -> Facility configuration.
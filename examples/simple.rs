@@ -12,6 +12,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         multiplayer_id: "".to_string(),
         title: "".to_string(),
         version_override: None,
+        ..Default::default()
     };
 
     // Connect to the Origin SDK server with the given configuration and the default port
@@ -6,3 +6,5 @@ pub mod random;
 mod macros;
 #[cfg(feature = "client")]
 pub mod sdk;
+#[cfg(feature = "client")]
+pub mod services;
@@ -2,7 +2,7 @@ use serde::{Deserialize, Serialize};
 
 use crate::protocol::common::Event;
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Achievement {
     #[serde(rename = "@Id")]
     pub id: String,
@@ -26,7 +26,7 @@ pub struct Achievement {
     pub expiration: String,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AchievementSet {
     #[serde(rename = "@Name")]
     pub name: String,
@@ -36,13 +36,13 @@ pub struct AchievementSet {
     pub achievement: Vec<Achievement>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AchievementSets {
     #[serde(rename = "AchievementSet", default)]
     pub achievement_set: Vec<AchievementSet>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct GrantAchievement {
     #[serde(rename = "@UserId")]
     pub user_id: u64,
@@ -56,7 +56,7 @@ pub struct GrantAchievement {
     pub achievement_code: String,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PostAchievementEvents {
     #[serde(rename = "@UserId")]
     pub user_id: u64,
@@ -77,3 +77,37 @@ pub struct QueryAchievements {
     #[serde(rename = "GameId", default)]
     pub game_id: Vec<String>,
 }
+
+impl Achievement {
+    /// Returns true if the achievement has been granted at least once
+    pub fn is_unlocked(&self) -> bool {
+        self.count > 0
+    }
+
+    /// Progress towards the achievement as a fraction between 0.0 and 1.0
+    pub fn completion(&self) -> f32 {
+        if self.is_unlocked() {
+            return 1.0;
+        }
+
+        if self.total <= 0 {
+            return 0.0;
+        }
+
+        (self.progress as f32 / self.total as f32).clamp(0.0, 1.0)
+    }
+}
+
+impl AchievementSets {
+    /// Iterate over the achievements of every set
+    pub fn achievements(&self) -> impl Iterator<Item = &Achievement> {
+        self.achievement_set
+            .iter()
+            .flat_map(|set| set.achievement.iter())
+    }
+
+    /// Find an achievement by its id across all sets
+    pub fn find(&self, id: &str) -> Option<&Achievement> {
+        self.achievements().find(|achievement| achievement.id == id)
+    }
+}
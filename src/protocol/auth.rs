@@ -73,7 +73,7 @@ pub struct Login {
     pub login_reason_code: LoginReasonCode,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum LoginReasonCode {
     #[serde(rename = "UNDEFINED")]
     Undefined,
@@ -1,3 +1,5 @@
+use num_derive::FromPrimitive;
+use num_traits::FromPrimitive;
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -9,26 +11,28 @@ pub struct BroadcastEvent {
 #[derive(Debug, Serialize, Deserialize)]
 pub struct BroadcastStart;
 
-#[derive(Debug, Serialize, Deserialize)]
+/// Discriminants are the assumed integer encoding of [`BroadcastStatus::status`]
+#[repr(i32)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, FromPrimitive)]
 pub enum BroadcastState {
     #[serde(rename = "DIALOG_OPEN")]
-    DialogOpen,
+    DialogOpen = 0,
     #[serde(rename = "DIALOG_CLOSED")]
-    DialogClosed,
+    DialogClosed = 1,
     #[serde(rename = "ACCOUNTLINKDIALOG_OPEN")]
-    AccountlinkdialogOpen,
+    AccountlinkdialogOpen = 2,
     #[serde(rename = "ACCOUNT_DISCONNECTED")]
-    AccountDisconnected,
+    AccountDisconnected = 3,
     #[serde(rename = "STARTED")]
-    Started,
+    Started = 4,
     #[serde(rename = "STOPPED")]
-    Stopped,
+    Stopped = 5,
     #[serde(rename = "BLOCKED")]
-    Blocked,
+    Blocked = 6,
     #[serde(rename = "START_PENDING")]
-    StartPending,
+    StartPending = 7,
     #[serde(rename = "ERROR")]
-    Error,
+    Error = 8,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -37,8 +41,42 @@ pub struct BroadcastStatus {
     pub status: i32,
 }
 
+impl BroadcastStatus {
+    /// Broadcast state encoded in the status
+    ///
+    /// The client does not document the encoding. The status is assumed to be the
+    /// index of the state in the order the LSX `BroadcastState` values are declared,
+    /// which is what the discriminants of [`BroadcastState`] follow.
+    pub fn state(&self) -> Option<BroadcastState> {
+        BroadcastState::from_i32(self.status)
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct BroadcastStop;
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct GetBroadcastStatus;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_broadcast_status_state() {
+        // Pins the assumed encoding, it is not verified against the client
+        let state = |status| BroadcastStatus { status }.state();
+
+        assert_eq!(state(0), Some(BroadcastState::DialogOpen));
+        assert_eq!(state(1), Some(BroadcastState::DialogClosed));
+        assert_eq!(state(2), Some(BroadcastState::AccountlinkdialogOpen));
+        assert_eq!(state(3), Some(BroadcastState::AccountDisconnected));
+        assert_eq!(state(4), Some(BroadcastState::Started));
+        assert_eq!(state(5), Some(BroadcastState::Stopped));
+        assert_eq!(state(6), Some(BroadcastState::Blocked));
+        assert_eq!(state(7), Some(BroadcastState::StartPending));
+        assert_eq!(state(8), Some(BroadcastState::Error));
+        assert_eq!(state(9), None);
+        assert_eq!(state(-1), None);
+    }
+}
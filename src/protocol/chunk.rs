@@ -18,7 +18,7 @@ pub struct AreChunksInstalledResponse {
     pub chunk_ids: Vec<i32>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum ChunkState {
     #[serde(rename = "UNKNOWN")]
     Unknown,
@@ -38,7 +38,7 @@ pub enum ChunkState {
     Busy,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ChunkStatus {
     #[serde(rename = "@ChunkId")]
     pub chunk_id: i32,
@@ -60,7 +60,7 @@ pub struct ChunkStatus {
     pub total_eta: i32,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum ChunkType {
     #[serde(rename = "UNKNOWN")]
     Unknown,
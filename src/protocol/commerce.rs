@@ -2,7 +2,7 @@ use serde::{Deserialize, Serialize};
 
 use crate::protocol::entitlements::Entitlement;
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Catalog {
     #[serde(rename = "@Name")]
     pub name: String,
@@ -18,7 +18,7 @@ pub struct Catalog {
     pub categories: Vec<Category>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Category {
     #[serde(rename = "@Type")]
     pub r#type: String,
@@ -53,7 +53,7 @@ pub struct Checkout {
 #[derive(Debug, Serialize, Deserialize)]
 pub struct DetermineCommerceCurrency {}
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Store {
     #[serde(rename = "Catalog", default)]
     pub catalogs: Vec<Catalog>,
@@ -131,7 +131,7 @@ pub struct GetWalletBalanceResponse {
     pub balance: i64,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Offer {
     #[serde(rename = "@Type")]
     pub r#type: String,
@@ -220,3 +220,41 @@ pub struct QueryOffersResponse {
     #[serde(rename = "Offer", default)]
     pub offers: Vec<Offer>,
 }
+
+impl Offer {
+    /// Price formatted for display, preferring the localized price from the server
+    pub fn display_price(&self) -> String {
+        format_price(&self.localized_price, self.price, &self.currency)
+    }
+
+    /// Price before discount formatted for display
+    pub fn display_original_price(&self) -> String {
+        format_price(
+            &self.localized_original_price,
+            self.original_price,
+            &self.currency,
+        )
+    }
+
+    /// Discount relative to the original price, in whole percent
+    pub fn discount_percent(&self) -> u32 {
+        if !self.b_is_discounted || self.original_price <= 0.0 || self.price >= self.original_price
+        {
+            return 0;
+        }
+
+        ((1.0 - self.price / self.original_price) * 100.0).round() as u32
+    }
+}
+
+fn format_price(localized: &str, price: f64, currency: &str) -> String {
+    if !localized.is_empty() {
+        return localized.to_string();
+    }
+
+    if currency.is_empty() {
+        format!("{:.2}", price)
+    } else {
+        format!("{:.2} {}", price, currency)
+    }
+}
@@ -1,3 +1,5 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
 use serde::{Deserialize, Serialize};
 
 use crate::protocol::errors::OriginError;
@@ -10,7 +12,7 @@ pub struct ErrorSuccess {
     pub description: String,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Event {
     #[serde(rename = "@EventId")]
     pub event_id: String,
@@ -18,7 +20,7 @@ pub struct Event {
     pub attributes: Vec<EventParam>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct EventParam {
     #[serde(rename = "@Name")]
     pub name: String,
@@ -97,3 +99,61 @@ pub enum Facility {
     #[serde(rename = "CONTENT")]
     Content,
 }
+
+/// Parse a `YYYY-MM-DDTHH:MM:SS` timestamp as sent by the server
+///
+/// Returns `None` for malformed values and for the zeroed dates the server uses
+/// when a field is not set, e.g. `0000-00-00T00:00:00`.
+pub fn parse_timestamp(value: &str) -> Option<SystemTime> {
+    let (date, time) = value.split_once('T')?;
+
+    let mut date = date.splitn(3, '-').map(str::parse::<i64>);
+    let (year, month, day) = (date.next()?.ok()?, date.next()?.ok()?, date.next()?.ok()?);
+
+    // Fractional seconds and time zone designators are ignored
+    let time = time.trim_end_matches('Z');
+    let time = time.split('.').next()?;
+    let mut time = time.splitn(3, ':').map(str::parse::<i64>);
+    let (hour, minute, second) = (time.next()?.ok()?, time.next()?.ok()?, time.next()?.ok()?);
+
+    if year < 1970
+        || !(1..=12).contains(&month)
+        || !(1..=31).contains(&day)
+        || !(0..24).contains(&hour)
+        || !(0..60).contains(&minute)
+        || !(0..=60).contains(&second)
+    {
+        return None;
+    }
+
+    // Days since the unix epoch, see http://howardhinnant.github.io/date_algorithms.html
+    let y = if month <= 2 { year - 1 } else { year };
+    let era = y.div_euclid(400);
+    let year_of_era = y - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    let days = era * 146097 + day_of_era - 719468;
+
+    let seconds = days * 86400 + hour * 3600 + minute * 60 + second;
+    Some(UNIX_EPOCH + Duration::from_secs(seconds as u64))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_timestamp() {
+        let time = parse_timestamp("2025-09-03T12:15:54").expect("Failed to parse");
+        assert_eq!(
+            time.duration_since(UNIX_EPOCH).unwrap().as_secs(),
+            1756901754
+        );
+
+        let time = parse_timestamp("1970-01-01T00:00:00Z").expect("Failed to parse");
+        assert_eq!(time, UNIX_EPOCH);
+
+        assert!(parse_timestamp("0000-00-00T00:00:08").is_none());
+        assert!(parse_timestamp("").is_none());
+    }
+}
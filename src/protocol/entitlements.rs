@@ -1,5 +1,9 @@
+use std::time::SystemTime;
+
 use serde::{Deserialize, Serialize};
 
+use crate::protocol::common::parse_timestamp;
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct ConsumeEntitlement {
     #[serde(rename = "@UserId")]
@@ -18,7 +22,7 @@ pub struct ConsumeEntitlementResponse {
     pub entitlement: Entitlement,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Entitlement {
     #[serde(rename = "@")]
     pub type_: String,
@@ -44,6 +48,19 @@ pub struct Entitlement {
     pub version: i32,
 }
 
+impl Entitlement {
+    /// Time at which the entitlement expires, `None` if it never does
+    pub fn expires_at(&self) -> Option<SystemTime> {
+        parse_timestamp(&self.expiration)
+    }
+
+    /// Returns true if the entitlement has an expiration date in the past
+    pub fn is_expired(&self) -> bool {
+        self.expires_at()
+            .is_some_and(|expiration| expiration <= SystemTime::now())
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct ExtendTrial {
     #[serde(rename = "@UserId")]
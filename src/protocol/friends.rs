@@ -10,7 +10,7 @@ pub struct AddRecentPlayers {
     pub player: Vec<u64>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Friend {
     #[serde(rename = "@UserId")]
     pub user_id: u64,
@@ -40,7 +40,7 @@ pub struct Friend {
     pub game_presence: String,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum FriendState {
     #[serde(rename = "UNKNOWN")]
     Unknown,
@@ -56,7 +56,7 @@ pub enum FriendState {
     Request,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FriendStatus {
     #[serde(rename = "@FriendId")]
     pub friend_id: u64,
@@ -1,6 +1,6 @@
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum ContentState {
     #[serde(rename = "UNKNOWN")]
     Unknown,
@@ -60,13 +60,70 @@ pub enum ContentState {
     DownloadQueued,
 }
 
+/// Coarse phase of a download or update, see [`ContentState::phase`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ContentPhase {
+    /// Waiting for the download to start, or paused
+    Queued,
+    Downloading,
+    /// Decrypting, unpacking, installing or verifying downloaded files
+    Installing,
+    Ready,
+    Error,
+}
+
+impl ContentState {
+    /// Classify the state into the phase shown to the user
+    pub fn phase(&self) -> ContentPhase {
+        match self {
+            // Nothing is known about the content yet, it has not started downloading
+            ContentState::Unknown
+            | ContentState::ReadyToDownload
+            | ContentState::ServerQueued
+            | ContentState::WaitingToDownload
+            | ContentState::DownloadQueued
+            | ContentState::PreparingDownload
+            | ContentState::ReadyToUpdate
+            | ContentState::DownloadPaused
+            | ContentState::UpdatePaused => ContentPhase::Queued,
+            ContentState::Downloading
+            | ContentState::FinalizingDownload
+            | ContentState::Updating => ContentPhase::Downloading,
+            ContentState::ReadyToActivate
+            | ContentState::ReadyToDecrypt
+            | ContentState::WaitingToDecrypt
+            | ContentState::Decrypting
+            | ContentState::ReadyToUnpack
+            | ContentState::Unpacking
+            | ContentState::ReadyToInstall
+            | ContentState::Installing
+            | ContentState::Verifying => ContentPhase::Installing,
+            ContentState::ReadyToPlay
+            | ContentState::ReadyToUse
+            | ContentState::Installed
+            | ContentState::Playing => ContentPhase::Ready,
+            ContentState::DownloadExpired
+            | ContentState::DecryptExpired
+            | ContentState::InvalidContent => ContentPhase::Error,
+        }
+    }
+
+    /// Returns true if the download or update was paused by the user
+    pub fn is_paused(&self) -> bool {
+        matches!(
+            self,
+            ContentState::DownloadPaused | ContentState::UpdatePaused
+        )
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct CoreContentUpdated {
     #[serde(rename = "Game", default)]
     pub games: Vec<Game>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Dlc {
     #[serde(rename = "@Name")]
     pub name: String,
@@ -76,7 +133,7 @@ pub struct Dlc {
     pub installed: bool,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Game {
     #[serde(rename = "@contentID")]
     pub content_id: String,
@@ -238,3 +295,18 @@ pub struct StartGame {
     #[serde(rename = "@CommandLine")]
     pub command_line: String,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_content_phase() {
+        assert_eq!(ContentState::ServerQueued.phase(), ContentPhase::Queued);
+        assert_eq!(ContentState::UpdatePaused.phase(), ContentPhase::Queued);
+        assert_eq!(ContentState::Updating.phase(), ContentPhase::Downloading);
+        assert_eq!(ContentState::Unpacking.phase(), ContentPhase::Installing);
+        assert_eq!(ContentState::Playing.phase(), ContentPhase::Ready);
+        assert_eq!(ContentState::InvalidContent.phase(), ContentPhase::Error);
+    }
+}
@@ -40,7 +40,7 @@ pub struct GroupEvent {
     pub members: Vec<Friend>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct GroupInfo {
     #[serde(rename = "@GroupName")]
     pub group_name: String,
@@ -74,7 +74,7 @@ pub struct GroupLeaveEvent {
     pub group_id: String,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum GroupType {
     #[serde(rename = "PUBLIC")]
     Public,
@@ -119,6 +119,7 @@ pub enum RequestBody {
     GetCatalog(GetCatalog),
     GetChunkPriority(GetChunkPriority),
     GetConfig(GetConfig),
+    GetEnvironment(GetEnvironment),
     GetGameInfo(GetGameInfo),
     GetGroupInfo(GetGroupInfo),
     GetInternetConnectedState(GetInternetConnectedState),
@@ -205,6 +206,7 @@ pub enum ResponseBody {
     GetCatalogResponse(GetCatalogResponse),
     GetChunkPriorityResponse(GetChunkPriorityResponse),
     GetConfigResponse(GetConfigResponse),
+    GetEnvironmentResponse(GetEnvironmentResponse),
     GetGameInfoResponse(GetGameInfoResponse),
     GetPresenceResponse(GetPresenceResponse),
     GetPresenceVisibilityResponse(GetPresenceVisibilityResponse),
@@ -310,6 +312,7 @@ request_response! {
     GetCatalog => GetCatalogResponse,
     GetChunkPriority => GetChunkPriorityResponse,
     GetConfig => GetConfigResponse,
+    GetEnvironment => GetEnvironmentResponse,
     GetGameInfo => GetGameInfoResponse,
     GetGroupInfo => GroupEnterEvent,
     GetInternetConnectedState => InternetConnectedState,
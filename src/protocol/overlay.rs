@@ -6,7 +6,7 @@ pub struct IgoEvent {
     pub state: IgoState,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum IgoState {
     #[serde(rename = "DOWN")]
     Down,
@@ -20,7 +20,7 @@ pub struct IgoUnavailable {
     pub reason: i32,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum IgoWindow {
     #[serde(rename = "LOGIN")]
     Login = 1,
@@ -110,4 +110,119 @@ impl ShowIgoWindow {
     pub fn default_show() -> bool {
         true
     }
+
+    /// Request for a window that takes no arguments
+    pub fn new(user_id: u64, window_id: IgoWindow) -> Self {
+        Self {
+            user_id,
+            window_id,
+            show: true,
+            flags: 0,
+            content_id: String::new(),
+            target_id: Vec::new(),
+            string: String::new(),
+            args: Vec::new(),
+            master_title_ids: Vec::new(),
+            categories: Vec::new(),
+            offers: Vec::new(),
+        }
+    }
+
+    /// Profile of another user
+    pub fn profile(user_id: u64, target_id: u64) -> Self {
+        Self {
+            target_id: vec![target_id],
+            ..Self::new(user_id, IgoWindow::Profile)
+        }
+    }
+
+    /// Send a friend request to another user
+    pub fn friend_request(user_id: u64, target_id: u64) -> Self {
+        Self {
+            target_id: vec![target_id],
+            ..Self::new(user_id, IgoWindow::FriendRequest)
+        }
+    }
+
+    /// Chat with other users
+    pub fn chat(user_id: u64, target_ids: Vec<u64>) -> Self {
+        Self {
+            target_id: target_ids,
+            ..Self::new(user_id, IgoWindow::Chat)
+        }
+    }
+
+    /// Chat with other users, with a message already typed in
+    pub fn compose_chat(user_id: u64, target_ids: Vec<u64>, message: impl Into<String>) -> Self {
+        Self {
+            target_id: target_ids,
+            string: message.into(),
+            ..Self::new(user_id, IgoWindow::ComposeChat)
+        }
+    }
+
+    /// Invite other users to the current game
+    pub fn invite(user_id: u64, target_ids: Vec<u64>) -> Self {
+        Self {
+            target_id: target_ids,
+            ..Self::new(user_id, IgoWindow::Invite)
+        }
+    }
+
+    /// Achievements of a user in the given games
+    pub fn achievements(user_id: u64, target_id: u64, master_title_ids: Vec<String>) -> Self {
+        Self {
+            target_id: vec![target_id],
+            master_title_ids,
+            ..Self::new(user_id, IgoWindow::Achievements)
+        }
+    }
+
+    /// Store filtered to the given categories and offers
+    pub fn store(user_id: u64, categories: Vec<String>, offers: Vec<String>) -> Self {
+        Self {
+            categories,
+            offers,
+            ..Self::new(user_id, IgoWindow::Store)
+        }
+    }
+
+    /// Checkout of the given offers
+    pub fn checkout(user_id: u64, offers: Vec<String>) -> Self {
+        Self {
+            offers,
+            ..Self::new(user_id, IgoWindow::Checkout)
+        }
+    }
+
+    /// Upsell dialog for the given offers
+    pub fn upsell(user_id: u64, offers: Vec<String>) -> Self {
+        Self {
+            offers,
+            ..Self::new(user_id, IgoWindow::Upsell)
+        }
+    }
+
+    /// Details page of the given games
+    pub fn game_details(user_id: u64, master_title_ids: Vec<String>) -> Self {
+        Self {
+            master_title_ids,
+            ..Self::new(user_id, IgoWindow::Gamedetails)
+        }
+    }
+
+    /// Web browser opened at a URL
+    pub fn browser(user_id: u64, url: impl Into<String>) -> Self {
+        Self {
+            string: url.into(),
+            ..Self::new(user_id, IgoWindow::Browser)
+        }
+    }
+
+    /// Close the window instead of opening it
+    #[must_use]
+    pub fn hide(mut self) -> Self {
+        self.show = false;
+        self
+    }
 }
@@ -14,7 +14,7 @@ pub struct CheckPermissionResponse {
     pub access: Access,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum Access {
     #[serde(rename = "UNKNOWN")]
     Unknown,
@@ -26,7 +26,7 @@ pub enum Access {
     FriendsOnly,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum Permission {
     #[serde(rename = "MULTIPLAYER")]
     Multiplayer,
@@ -35,3 +35,16 @@ pub enum Permission {
     #[serde(rename = "TRIAL")]
     Trial,
 }
+
+impl Access {
+    /// Returns true if the permission allows interacting with a user
+    ///
+    /// [`Access::Unknown`] is treated as denied.
+    pub const fn allows(&self, is_friend: bool) -> bool {
+        match self {
+            Access::Granted => true,
+            Access::FriendsOnly => is_friend,
+            Access::Denied | Access::Unknown => false,
+        }
+    }
+}
@@ -77,7 +77,7 @@ pub struct OnlineStatusEvent {
     pub is_online: bool,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum Presence {
     #[serde(rename = "UNKNOWN")]
     Unknown,
@@ -8,7 +8,7 @@ pub struct GetProfile {
     pub index: i32,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct GetProfileResponse {
     #[serde(rename = "@UserIndex")]
     pub user_index: i32,
@@ -36,7 +36,7 @@ pub struct SteamActivateOverlayToStoreEvent {
     pub flag: SteamOverlayToStoreFlag,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum SteamOverlayToStoreFlag {
     #[serde(rename = "NONE")]
     None,
@@ -11,6 +11,9 @@ pub struct GetConfigResponse {
     pub services: Vec<Service>,
 }
 
+#[derive(Debug, Serialize, Deserialize)]
+pub struct GetEnvironment;
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct GetEnvironmentResponse {
     #[serde(rename = "@Environment")]
@@ -35,7 +38,7 @@ pub struct GetSettingResponse {
 #[derive(Debug, Serialize, Deserialize)]
 pub struct GetSettings;
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct GetSettingsResponse {
     #[serde(rename = "@Language")]
     pub language: String,
@@ -83,7 +86,7 @@ pub struct Service {
     pub facility: Facility,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum Setting {
     #[serde(rename = "LANGUAGE")]
     Language,
@@ -99,7 +102,111 @@ pub enum Setting {
     IsManualOffline,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+/// Backend environment the client is connected to
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Environment {
+    Production,
+    Integration,
+    /// Any other environment, as reported by the client
+    Other(String),
+}
+
+impl Environment {
+    /// Parse the environment reported by the client, case-insensitively
+    pub fn parse(value: &str) -> Self {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "production" | "prod" => Environment::Production,
+            "integration" | "int" => Environment::Integration,
+            _ => Environment::Other(value.trim().to_string()),
+        }
+    }
+
+    /// Returns true if the client is connected to the production environment
+    pub fn is_production(&self) -> bool {
+        *self == Environment::Production
+    }
+}
+
+/// Language tag reported by the client, e.g. `en_US`
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct LanguageTag(String);
+
+impl LanguageTag {
+    /// Parse a language tag, accepting both `en_US` and `en-US`
+    pub fn parse(value: &str) -> Option<Self> {
+        let value = value.trim().replace('-', "_");
+        let (language, region) = match value.split_once('_') {
+            Some((language, region)) => (language, Some(region)),
+            None => (value.as_str(), None),
+        };
+
+        let valid_language =
+            (2..=3).contains(&language.len()) && language.bytes().all(|b| b.is_ascii_alphabetic());
+        let valid_region = region.is_none_or(|region| {
+            !region.is_empty() && region.bytes().all(|b| b.is_ascii_alphanumeric())
+        });
+
+        if !valid_language || !valid_region {
+            return None;
+        }
+
+        Some(match region {
+            Some(region) => Self(format!(
+                "{}_{}",
+                language.to_ascii_lowercase(),
+                region.to_ascii_uppercase()
+            )),
+            None => Self(language.to_ascii_lowercase()),
+        })
+    }
+
+    /// Language subtag, e.g. `en`
+    pub fn language(&self) -> &str {
+        self.0.split('_').next().unwrap_or(&self.0)
+    }
+
+    /// Region subtag, e.g. `US`
+    pub fn region(&self) -> Option<&str> {
+        self.0.split_once('_').map(|(_, region)| region)
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl std::fmt::Display for LanguageTag {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl GetSettingsResponse {
+    /// Languages of the client, the first one is the active one
+    pub fn languages(&self) -> Vec<LanguageTag> {
+        self.language
+            .split(',')
+            .filter_map(LanguageTag::parse)
+            .collect()
+    }
+
+    pub fn environment(&self) -> Environment {
+        Environment::parse(&self.environment)
+    }
+}
+
+impl GetSettingResponse {
+    /// Value of a boolean setting such as [`Setting::IsIgoEnabled`]
+    pub fn as_bool(&self) -> Option<bool> {
+        match self.setting.trim().to_ascii_lowercase().as_str() {
+            "true" | "1" => Some(true),
+            "false" | "0" => Some(false),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Image {
     #[serde(rename = "@ImageId")]
     pub image_id: String,
@@ -128,3 +235,20 @@ pub struct QueryImageResponse {
     #[serde(rename = "Image", default)]
     pub images: Vec<Image>,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_language_tag() {
+        let tag = LanguageTag::parse("en-us").expect("Failed to parse");
+        assert_eq!(tag.as_str(), "en_US");
+        assert_eq!(tag.language(), "en");
+        assert_eq!(tag.region(), Some("US"));
+
+        assert_eq!(LanguageTag::parse("de").unwrap().region(), None);
+        assert!(LanguageTag::parse("").is_none());
+        assert!(LanguageTag::parse("english_").is_none());
+    }
+}
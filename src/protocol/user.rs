@@ -30,7 +30,7 @@ pub struct UnblockUser {
     pub user_id_to_unblock: u64,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct User {
     #[serde(rename = "@EAID")]
     pub eaid: String,
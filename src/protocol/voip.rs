@@ -6,7 +6,7 @@ pub struct EnableVoip {
     pub enable: bool,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum EnumMuteState {
     #[serde(rename = "NONE")]
     None,
@@ -98,3 +98,46 @@ pub struct VoipStatusEvent {
     #[serde(rename = "@UserId")]
     pub user_id: u64,
 }
+
+impl EnumMuteState {
+    /// Build a mute state from the local and remote mute flags
+    pub const fn from_flags(locally: bool, remotely: bool) -> Self {
+        match (locally, remotely) {
+            (false, false) => EnumMuteState::Unmuted,
+            (true, false) => EnumMuteState::MutedLocally,
+            (false, true) => EnumMuteState::MutedRemotely,
+            (true, true) => EnumMuteState::MutedLocallyAndRemotely,
+        }
+    }
+
+    /// Returns true if the current user muted the user
+    pub const fn is_muted_locally(&self) -> bool {
+        matches!(
+            self,
+            EnumMuteState::MutedLocally | EnumMuteState::MutedLocallyAndRemotely
+        )
+    }
+
+    /// Returns true if the user is muted on their own side
+    pub const fn is_muted_remotely(&self) -> bool {
+        matches!(
+            self,
+            EnumMuteState::MutedRemotely | EnumMuteState::MutedLocallyAndRemotely
+        )
+    }
+
+    /// Apply a mute change reported by a [`VoipStatusEvent`]
+    ///
+    /// Returns `None` if the status does not change the mute state.
+    pub const fn with_status(self, status: &VoipStatus) -> Option<Self> {
+        let (locally, remotely) = match status {
+            VoipStatus::UserMutedLocally => (true, self.is_muted_remotely()),
+            VoipStatus::UserUnmutedLocally => (false, self.is_muted_remotely()),
+            VoipStatus::UserMutedRemotely => (self.is_muted_locally(), true),
+            VoipStatus::UserUnmutedRemotely => (self.is_muted_locally(), false),
+            _ => return None,
+        };
+
+        Some(Self::from_flags(locally, remotely))
+    }
+}
@@ -13,6 +13,7 @@ impl Random {
         Self { seed }
     }
 
+    #[allow(clippy::should_implement_trait)]
     pub fn next(&mut self) -> u32 {
         self.seed = self.seed.wrapping_mul(MULTIPLIER).wrapping_add(INCREMENT);
         (self.seed >> 16) & RAND_MAX
@@ -25,14 +25,20 @@ use tracing::{debug, error, info, warn};
 use crate::{
     crypto::Crypto,
     protocol::{
-        auth::ChallengeResponse, errors::OriginError, Event, EventBody, Lsx, Message, Request,
-        RequestBody, RequestResponse, Response, ResponseBody,
+        auth::ChallengeResponse,
+        errors::OriginError,
+        profile::{GetProfile, GetProfileResponse},
+        Event, EventBody, Lsx, Message, Request, RequestBody, RequestResponse, Response,
+        ResponseBody,
     },
 };
 
 /// Default port for the Origin SDK
 pub const ORIGIN_SDK_PORT: u16 = 3216;
 
+/// Events held back while the event channel is full, newer events are dropped beyond that
+const EVENT_QUEUE_CAPACITY: usize = 10_000;
+
 #[derive(Error, Debug)]
 pub enum SdkError {
     #[error("{0:?}: {1}")]
@@ -47,16 +53,26 @@ pub enum SdkError {
     #[error("Deserialization error: {0}")]
     Deserialization(#[from] quick_xml::DeError),
 
+    #[error("JSON error: {0}")]
+    Json(#[from] serde_json::Error),
+
     #[error("Crypto error: {0}")]
     Crypto(#[from] crate::crypto::CryptoError),
 
+    #[error("Storage error: {0}")]
+    Storage(std::io::Error),
+
     #[error("{0}")]
     Other(String),
 }
 
-type SdkResult<T> = Result<T, SdkError>;
+pub(crate) type SdkResult<T> = Result<T, SdkError>;
 
 /// Configuration for the Origin SDK client
+///
+/// Fields added in later versions have defaults, construct the config with
+/// `..Default::default()` to stay compatible.
+#[derive(Debug, Clone, Default)]
 pub struct ClientConfig {
     /// Can be contentId, masterTitleId, or offerId
     pub content_id: String,
@@ -64,6 +80,11 @@ pub struct ClientConfig {
     pub multiplayer_id: String,
     pub title: String,
     pub version_override: Option<String>,
+    /// Fetch and cache the profile of the current user right after connecting
+    ///
+    /// A failure, e.g. because no user is logged in, is logged and does not fail the
+    /// connection.
+    pub fetch_profile: bool,
 }
 
 /// Shared state for tracking requests that are awaiting responses
@@ -72,22 +93,30 @@ pub struct ClientConfig {
 /// through which the response will be delivered
 type PendingRequests = Arc<Mutex<HashMap<u64, oneshot::Sender<Response>>>>;
 
+/// Profile of the current user, dropped by the reader task when the profile changes
+/// or a user logs in
+type CachedProfile = Arc<Mutex<Option<GetProfileResponse>>>;
+
 /// The client for interacting with the Origin SDK protocol.
 pub struct OriginSdk {
     /// Shared handle for writing messages to the server
     writer: Arc<Mutex<OwnedWriteHalf>>,
     /// Background task that continiously reads messages from the server
     reader_handle: JoinHandle<()>,
+    /// Background task that moves received events into the event channel
+    forwarder_handle: JoinHandle<()>,
     /// Pending requests waiting for server responses
     pending_requests: PendingRequests,
     next_id: AtomicU64,
     crypto: Crypto,
+    profile: CachedProfile,
 }
 
 impl Drop for OriginSdk {
     fn drop(&mut self) {
-        // Abort the background reader task when the client is dropped
+        // Abort the background tasks when the client is dropped
         self.reader_handle.abort();
+        self.forwarder_handle.abort();
     }
 }
 
@@ -107,31 +136,50 @@ impl OriginSdk {
         let mut reader = BufReader::new(read_half);
         let mut writer = write_half;
         let mut crypto = Crypto::new(0);
+        let fetch_profile = config.fetch_profile;
 
         // Server requires a challenge/response authentication sequence
         // before normal requests can be sent.
         Self::perform_challenge(config, &mut reader, &mut writer, &mut crypto).await?;
 
         let pending_requests: PendingRequests = Arc::new(Mutex::new(HashMap::new()));
+        let profile: CachedProfile = Arc::new(Mutex::new(None));
 
         let (event_tx, event_rx) = mpsc::channel(100);
 
+        // Events are queued in between, so the reader never waits for the event channel
+        // and responses keep arriving while an event handler awaits a request. If the
+        // game stops reading events altogether, the queue is capped and further events
+        // are dropped instead of growing memory or stalling responses.
+        let (queue_tx, queue_rx) = mpsc::channel(EVENT_QUEUE_CAPACITY);
+        let forwarder_handle = tokio::spawn(Self::forward_events(queue_rx, event_tx));
+
         // Spawn the background reader lopp
         let reader_handle = tokio::spawn(Self::reader_task(
             reader,
             pending_requests.clone(),
             crypto.clone(),
-            event_tx,
+            profile.clone(),
+            queue_tx,
         ));
 
         let sdk = OriginSdk {
             writer: Arc::new(Mutex::new(writer)),
             reader_handle,
+            forwarder_handle,
             pending_requests,
             next_id: AtomicU64::new(1),
             crypto,
+            profile,
         };
 
+        if fetch_profile {
+            // The cache stays empty and is filled on first use instead
+            if let Err(e) = sdk.refresh_profile().await {
+                warn!("Failed to fetch the current user profile: {}", e);
+            }
+        }
+
         Ok((sdk, event_rx))
     }
 
@@ -263,6 +311,7 @@ impl OriginSdk {
         mut reader: BufReader<OwnedReadHalf>,
         pending_requests: PendingRequests,
         crypto: Crypto,
+        profile: CachedProfile,
         event_tx: mpsc::Sender<Event>,
     ) {
         loop {
@@ -302,12 +351,27 @@ impl OriginSdk {
                     // Responses are matched against their pending IDs
                     match lsx.message {
                         Message::Event(event) => {
-                            if event_tx.is_closed() {
-                                continue;
+                            // The cached profile is fetched again on next use
+                            match &event.body {
+                                EventBody::ProfileEvent(changed) => {
+                                    let mut profile = profile.lock().await;
+                                    if profile
+                                        .as_ref()
+                                        .is_some_and(|profile| profile.user_id == changed.user_id)
+                                    {
+                                        debug!("Profile changed: {:?}", changed.changed);
+                                        profile.take();
+                                    }
+                                }
+                                // The user may have logged out or switched accounts
+                                EventBody::Login(_) => {
+                                    profile.lock().await.take();
+                                }
+                                _ => {}
                             }
 
-                            if let Err(e) = event_tx.send(event).await {
-                                warn!("Failed to send event: {}", e);
+                            if let Err(e) = event_tx.try_send(event) {
+                                warn!("Dropping event, events are not being read: {}", e);
                             }
                         }
                         Message::Response(response) => {
@@ -331,6 +395,19 @@ impl OriginSdk {
         }
     }
 
+    async fn forward_events(mut queue: mpsc::Receiver<Event>, event_tx: mpsc::Sender<Event>) {
+        while let Some(event) = queue.recv().await {
+            // Keep draining the queue once nobody listens to events anymore
+            if event_tx.is_closed() {
+                continue;
+            }
+
+            if let Err(e) = event_tx.send(event).await {
+                warn!("Failed to send event: {}", e);
+            }
+        }
+    }
+
     async fn read_message(reader: &mut BufReader<OwnedReadHalf>) -> SdkResult<Vec<u8>> {
         let mut buf = Vec::new();
 
@@ -348,6 +425,52 @@ impl OriginSdk {
         Ok(())
     }
 
+    /// Profile of the current user, fetched once and cached
+    ///
+    /// The cache is dropped whenever a [`EventBody::ProfileEvent`] for the user or a
+    /// [`EventBody::Login`] is received, so the next call fetches the updated profile.
+    pub async fn current_user(&self) -> SdkResult<GetProfileResponse> {
+        if let Some(profile) = self.profile.lock().await.as_ref() {
+            return Ok(profile.clone());
+        }
+
+        self.refresh_profile().await
+    }
+
+    /// Fetch the profile of the current user and update the cache
+    pub async fn refresh_profile(&self) -> SdkResult<GetProfileResponse> {
+        let profile = self.request(GetProfile { index: 0 }).await?;
+        *self.profile.lock().await = Some(profile.clone());
+
+        Ok(profile)
+    }
+
+    /// Drop the cached profile, the next call to [`OriginSdk::current_user`] fetches it
+    pub async fn clear_profile(&self) {
+        self.profile.lock().await.take();
+    }
+
+    /// Cached profile of the current user, without fetching it
+    pub async fn cached_profile(&self) -> Option<GetProfileResponse> {
+        self.profile.lock().await.clone()
+    }
+
+    /// Send a request built from the profile of the current user
+    ///
+    /// ```rust,ignore
+    /// let friends = client
+    ///     .request_for_current_user(|user| QueryFriends { user_id: user.user_id })
+    ///     .await?;
+    /// ```
+    pub async fn request_for_current_user<T, F>(&self, build: F) -> SdkResult<T::Response>
+    where
+        T: RequestResponse + Into<RequestBody>,
+        F: FnOnce(&GetProfileResponse) -> T,
+    {
+        let profile = self.current_user().await?;
+        self.request(build(&profile)).await
+    }
+
     #[deprecated = "use `request()` instead"]
     pub async fn send_request<T>(&self, body: T) -> SdkResult<T::Response>
     where
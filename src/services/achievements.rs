@@ -0,0 +1,373 @@
+use std::{path::PathBuf, sync::Arc};
+
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+use tracing::{debug, error, warn};
+
+use crate::{
+    protocol::{
+        achievements::{
+            Achievement, AchievementSets, GrantAchievement, PostAchievementEvents,
+            QueryAchievements,
+        },
+        common::Event,
+        errors::OriginError,
+        EventBody,
+    },
+    sdk::{OriginSdk, SdkError, SdkResult},
+    services::current_user_ids,
+};
+
+/// Result of an operation that is queued while the user is offline
+#[derive(Debug)]
+pub enum Delivery<T> {
+    /// The request reached the server
+    Sent(T),
+    /// The server could not be reached, the request was persisted and will be retried
+    Queued,
+}
+
+/// A request waiting to be delivered once the user is back online
+#[derive(Debug, Clone, Serialize, Deserialize)]
+enum QueuedRequest {
+    GrantAchievement(GrantAchievement),
+    PostAchievementEvents(PostAchievementEvents),
+}
+
+/// On-disk representation of the offline queue
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(rename = "AchievementQueue")]
+struct AchievementQueue {
+    #[serde(rename = "$value", default)]
+    requests: Vec<QueuedRequest>,
+}
+
+/// Achievement service for the current user
+///
+/// Caches the [`AchievementSets`] of the user, grants achievements by code and
+/// keeps grants and events that failed with [`OriginError::NoNetwork`] or
+/// [`OriginError::NotLoggedIn`] in a queue persisted at `queue_path`. The queue
+/// is flushed when an [`EventBody::OnlineStatusEvent`] reports the user online.
+pub struct Achievements {
+    sdk: Arc<OriginSdk>,
+    user_id: u64,
+    persona_id: u64,
+    queue_path: PathBuf,
+    sets: Mutex<Option<AchievementSets>>,
+    queue: Mutex<Vec<QueuedRequest>>,
+}
+
+impl Achievements {
+    /// Create the service for the current user, restoring any queue left at `queue_path`
+    pub async fn new(sdk: Arc<OriginSdk>, queue_path: impl Into<PathBuf>) -> SdkResult<Self> {
+        let (user_id, persona_id) = current_user_ids(&sdk).await?;
+        let queue_path = queue_path.into();
+        let queue = Self::load_queue(&queue_path).await?;
+
+        if !queue.is_empty() {
+            debug!("Restored {} queued achievement requests", queue.len());
+        }
+
+        Ok(Self {
+            sdk,
+            user_id,
+            persona_id,
+            queue_path,
+            sets: Mutex::new(None),
+            queue: Mutex::new(queue),
+        })
+    }
+
+    /// Query the achievement sets from the server and replace the cache
+    pub async fn refresh(&self) -> SdkResult<AchievementSets> {
+        let sets = self
+            .sdk
+            .request(QueryAchievements {
+                user_id: self.user_id,
+                persona_id: self.persona_id,
+                all: true,
+                game_id: Vec::new(),
+            })
+            .await?;
+
+        *self.sets.lock().await = Some(sets.clone());
+        Ok(sets)
+    }
+
+    /// Cached achievement sets, queried from the server on first use
+    pub async fn sets(&self) -> SdkResult<AchievementSets> {
+        if let Some(sets) = self.sets.lock().await.as_ref() {
+            return Ok(sets.clone());
+        }
+
+        self.refresh().await
+    }
+
+    /// Achievements that have been granted
+    pub async fn unlocked(&self) -> SdkResult<Vec<Achievement>> {
+        let sets = self.sets().await?;
+        Ok(sets
+            .achievements()
+            .filter(|a| a.is_unlocked())
+            .cloned()
+            .collect())
+    }
+
+    /// Achievements that have not been granted yet
+    pub async fn locked(&self) -> SdkResult<Vec<Achievement>> {
+        let sets = self.sets().await?;
+        Ok(sets
+            .achievements()
+            .filter(|a| !a.is_unlocked())
+            .cloned()
+            .collect())
+    }
+
+    /// Progress of a single achievement between 0.0 and 1.0
+    pub async fn progress(&self, id: &str) -> SdkResult<Option<f32>> {
+        let sets = self.sets().await?;
+        Ok(sets.find(id).map(Achievement::completion))
+    }
+
+    /// Grant an achievement by its code
+    ///
+    /// The code is the numeric achievement id, as listed in the achievement sets. The
+    /// request is queued instead of failing when the user is offline or the client
+    /// cannot be reached.
+    pub async fn grant(&self, code: &str, progress: i32) -> SdkResult<Delivery<Achievement>> {
+        let request = GrantAchievement {
+            user_id: self.user_id,
+            persona_id: self.persona_id,
+            achievement_id: achievement_id(code)?,
+            progress,
+            achievement_code: code.to_string(),
+        };
+
+        match self.sdk.request(request.clone()).await {
+            Ok(achievement) => {
+                self.update_cached(&achievement).await;
+                Ok(Delivery::Sent(achievement))
+            }
+            Err(err) if is_retryable(&err) => {
+                self.enqueue(QueuedRequest::GrantAchievement(request))
+                    .await?;
+                Ok(Delivery::Queued)
+            }
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Post achievement events for the current user
+    ///
+    /// The request is queued instead of failing when the user is offline or the client
+    /// cannot be reached.
+    pub async fn post_events(&self, events: Vec<Event>) -> SdkResult<Delivery<()>> {
+        let request = PostAchievementEvents {
+            user_id: self.user_id,
+            persona_id: self.persona_id,
+            events,
+        };
+
+        match self.sdk.request(request.clone()).await {
+            Ok(_) => Ok(Delivery::Sent(())),
+            Err(err) if is_retryable(&err) => {
+                self.enqueue(QueuedRequest::PostAchievementEvents(request))
+                    .await?;
+                Ok(Delivery::Queued)
+            }
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Number of requests waiting in the offline queue
+    pub async fn pending(&self) -> usize {
+        self.queue.lock().await.len()
+    }
+
+    /// Deliver queued requests in order
+    ///
+    /// Stops at the first request that fails because the user is still offline or the
+    /// client cannot be reached, e.g. on a timeout. Requests rejected by the server for
+    /// any other reason are dropped, as retrying them would block the rest of the
+    /// queue. Returns the number of delivered requests.
+    pub async fn flush(&self) -> SdkResult<usize> {
+        let mut queue = self.queue.lock().await;
+        let mut delivered = 0;
+
+        while let Some(request) = queue.first().cloned() {
+            let result = match request {
+                QueuedRequest::GrantAchievement(request) => match self.sdk.request(request).await {
+                    Ok(achievement) => {
+                        self.update_cached(&achievement).await;
+                        Ok(())
+                    }
+                    Err(err) => Err(err),
+                },
+                QueuedRequest::PostAchievementEvents(request) => {
+                    self.sdk.request(request).await.map(|_| ())
+                }
+            };
+
+            match result {
+                Ok(()) => delivered += 1,
+                Err(err) if is_retryable(&err) => break,
+                Err(err) => warn!("Dropping queued achievement request: {}", err),
+            }
+
+            queue.remove(0);
+        }
+
+        Self::store_queue(&self.queue_path, &queue).await?;
+        Ok(delivered)
+    }
+
+    /// Feed an event received from the server into the service
+    pub async fn handle_event(&self, event: &EventBody) {
+        match event {
+            EventBody::AchievementSets(sets) => {
+                *self.sets.lock().await = Some(sets.clone());
+            }
+            EventBody::OnlineStatusEvent(status) if status.is_online => {
+                if let Err(err) = self.flush().await {
+                    error!("Failed to flush achievement queue: {}", err);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    async fn update_cached(&self, achievement: &Achievement) {
+        let mut sets = self.sets.lock().await;
+        let Some(sets) = sets.as_mut() else {
+            return;
+        };
+
+        let cached = sets
+            .achievement_set
+            .iter_mut()
+            .flat_map(|set| set.achievement.iter_mut())
+            .find(|cached| cached.id == achievement.id);
+
+        if let Some(cached) = cached {
+            *cached = achievement.clone();
+        }
+    }
+
+    async fn enqueue(&self, request: QueuedRequest) -> SdkResult<()> {
+        let mut queue = self.queue.lock().await;
+        queue.push(request);
+
+        debug!("Queued achievement request, {} pending", queue.len());
+        Self::store_queue(&self.queue_path, &queue).await
+    }
+
+    async fn load_queue(path: &PathBuf) -> SdkResult<Vec<QueuedRequest>> {
+        let xml = match tokio::fs::read_to_string(path).await {
+            Ok(xml) => xml,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(err) => return Err(SdkError::Storage(err)),
+        };
+
+        let queue: AchievementQueue = quick_xml::de::from_str(&xml)?;
+        Ok(queue.requests)
+    }
+
+    async fn store_queue(path: &PathBuf, requests: &[QueuedRequest]) -> SdkResult<()> {
+        let queue = AchievementQueue {
+            requests: requests.to_vec(),
+        };
+
+        let xml = quick_xml::se::to_string(&queue)?;
+        tokio::fs::write(path, xml).await.map_err(SdkError::Storage)
+    }
+}
+
+/// Numeric id sent along with the code of an achievement
+fn achievement_id(code: &str) -> SdkResult<i32> {
+    code.trim().parse().map_err(|_| {
+        SdkError::Other(format!(
+            "Achievement code {:?} is not a numeric achievement id",
+            code
+        ))
+    })
+}
+
+/// Returns false only if the server definitively rejected the request
+///
+/// Transport errors, timeouts and a user that is offline or logged out are worth
+/// retrying later.
+fn is_retryable(err: &SdkError) -> bool {
+    match err {
+        SdkError::OriginError(OriginError::NoNetwork | OriginError::NotLoggedIn, _) => true,
+        SdkError::OriginError(..) => false,
+        _ => true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::common::EventParam;
+
+    #[test]
+    fn test_queue_roundtrip() {
+        let queue = AchievementQueue {
+            requests: vec![
+                QueuedRequest::GrantAchievement(GrantAchievement {
+                    user_id: 1,
+                    persona_id: 2,
+                    achievement_id: 3,
+                    progress: 4,
+                    achievement_code: "3".to_string(),
+                }),
+                QueuedRequest::PostAchievementEvents(PostAchievementEvents {
+                    user_id: 1,
+                    persona_id: 2,
+                    events: vec![Event {
+                        event_id: "kills".to_string(),
+                        attributes: vec![EventParam {
+                            name: "count".to_string(),
+                            value: "10".to_string(),
+                        }],
+                    }],
+                }),
+            ],
+        };
+
+        let xml = quick_xml::se::to_string(&queue).expect("Failed to serialize");
+        let restored: AchievementQueue =
+            quick_xml::de::from_str(&xml).expect("Failed to deserialize");
+
+        assert_eq!(restored.requests.len(), 2);
+        assert!(matches!(
+            &restored.requests[0],
+            QueuedRequest::GrantAchievement(grant) if grant.achievement_code == "3"
+        ));
+        assert!(matches!(
+            &restored.requests[1],
+            QueuedRequest::PostAchievementEvents(post) if post.events[0].attributes[0].value == "10"
+        ));
+    }
+
+    #[test]
+    fn test_achievement_id() {
+        assert_eq!(achievement_id("3").unwrap(), 3);
+        assert_eq!(achievement_id(" 42 ").unwrap(), 42);
+        assert!(achievement_id("first_blood").is_err());
+        assert!(achievement_id("").is_err());
+    }
+
+    #[test]
+    fn test_is_retryable() {
+        let origin = |code| SdkError::OriginError(code, String::new());
+
+        assert!(is_retryable(&origin(OriginError::NoNetwork)));
+        assert!(is_retryable(&origin(OriginError::NotLoggedIn)));
+        assert!(is_retryable(&SdkError::Other("Timeout".to_string())));
+        assert!(is_retryable(&SdkError::Network(std::io::Error::from(
+            std::io::ErrorKind::ConnectionReset
+        ))));
+
+        assert!(!is_retryable(&origin(OriginError::InvalidArgument)));
+    }
+}
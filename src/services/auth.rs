@@ -0,0 +1,242 @@
+use std::{
+    collections::HashMap,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use tokio::sync::Mutex;
+use tracing::{debug, warn};
+
+use crate::{
+    protocol::{
+        auth::{GetAuthCode, GetAuthToken, Login, LoginReasonCode},
+        EventBody,
+    },
+    sdk::{OriginSdk, SdkError, SdkResult},
+    services::current_user_ids,
+};
+
+/// Default time an access token is reused before a new one is requested
+///
+/// The client does not report how long its tokens stay valid, so this is a
+/// conservative guess. Pass the lifetime used by the backend to [`Auth::new`] if known.
+pub const DEFAULT_TOKEN_TTL: Duration = Duration::from_secs(10 * 60);
+
+/// An access token returned by [`GetAuthToken`]
+#[derive(Debug, Clone)]
+pub struct AccessToken {
+    pub value: String,
+    pub expires_at: Instant,
+}
+
+impl AccessToken {
+    /// Returns true if the token should be requested again
+    pub fn is_expired(&self) -> bool {
+        self.is_expired_at(Instant::now())
+    }
+
+    fn is_expired_at(&self, now: Instant) -> bool {
+        now >= self.expires_at
+    }
+}
+
+/// The access token of the current user, if one was obtained and is still usable
+#[derive(Debug, Default)]
+struct TokenCache {
+    token: Option<AccessToken>,
+}
+
+impl TokenCache {
+    /// Cached token, `None` if there is none or it expired
+    fn get(&self, now: Instant) -> Option<AccessToken> {
+        self.token
+            .as_ref()
+            .filter(|token| !token.is_expired_at(now))
+            .cloned()
+    }
+
+    /// Cache a token obtained at `now` for `ttl`
+    fn store(&mut self, value: String, ttl: Duration, now: Instant) -> AccessToken {
+        let token = AccessToken {
+            value,
+            expires_at: now + ttl,
+        };
+
+        self.token = Some(token.clone());
+        token
+    }
+
+    fn invalidate(&mut self) {
+        self.token = None;
+    }
+
+    /// Drop the token on every login change, it may belong to the previous user or
+    /// be the one the client failed to refresh
+    fn handle_login(&mut self, login: &Login) {
+        if login.login_reason_code == LoginReasonCode::AccesstokenRefreshError {
+            warn!("Client failed to refresh its access token, dropping it");
+        } else if !login.is_logged_in {
+            debug!("User logged out, dropping access token");
+        }
+
+        self.invalidate();
+    }
+}
+
+/// OAuth helper for logging into game backends
+///
+/// Auth codes are single use and always requested fresh with [`GetAuthCode`], using
+/// the `AppendAuthSource` setting configured for the client id. [`GetAuthToken`] does
+/// not take a client id, so a single access token of the current user is cached for
+/// `ttl`. It is dropped on every [`EventBody::Login`], including a
+/// [`LoginReasonCode::AccesstokenRefreshError`], and requested again on next use.
+pub struct Auth {
+    sdk: Arc<OriginSdk>,
+    ttl: Duration,
+    clients: Mutex<HashMap<String, bool>>,
+    token: Mutex<TokenCache>,
+}
+
+impl Auth {
+    /// Create the auth helper for the current user
+    ///
+    /// The user is resolved again on every request, so the helper follows user changes.
+    pub async fn new(sdk: Arc<OriginSdk>, ttl: Duration) -> SdkResult<Self> {
+        // Fail early if there is no user
+        current_user_ids(&sdk).await?;
+
+        Ok(Self {
+            sdk,
+            ttl,
+            clients: Mutex::new(HashMap::new()),
+            token: Mutex::new(TokenCache::default()),
+        })
+    }
+
+    /// Configure whether auth codes for a client id include the auth source
+    pub async fn configure_client(&self, client_id: impl Into<String>, append_auth_source: bool) {
+        self.clients
+            .lock()
+            .await
+            .insert(client_id.into(), append_auth_source);
+    }
+
+    /// Request an auth code to hand to the game server for the given client id and scopes
+    pub async fn auth_code_for(&self, client_id: &str, scopes: &[&str]) -> SdkResult<String> {
+        let append_auth_source = self
+            .clients
+            .lock()
+            .await
+            .get(client_id)
+            .copied()
+            .unwrap_or(false);
+
+        let (user_id, _) = current_user_ids(&self.sdk).await?;
+
+        let response = self
+            .sdk
+            .request(GetAuthCode {
+                user_id,
+                client_id: client_id.to_string(),
+                scope: scopes.join(" "),
+                append_auth_source,
+            })
+            .await?;
+
+        if response.value.is_empty() {
+            return Err(SdkError::Other(format!(
+                "Received an empty auth code for {}",
+                client_id
+            )));
+        }
+
+        Ok(response.value)
+    }
+
+    /// Return the cached access token, requesting a new one if there is none or it expired
+    pub async fn access_token(&self) -> SdkResult<AccessToken> {
+        let mut cache = self.token.lock().await;
+
+        if let Some(token) = cache.get(Instant::now()) {
+            return Ok(token);
+        }
+
+        let response = self.sdk.request(GetAuthToken).await?;
+
+        if response.value.is_empty() {
+            return Err(SdkError::Other(
+                "Received an empty access token".to_string(),
+            ));
+        }
+
+        debug!("Obtained access token, reused for {:?}", self.ttl);
+        Ok(cache.store(response.value, self.ttl, Instant::now()))
+    }
+
+    /// Drop the cached access token, the next call requests a new one
+    pub async fn invalidate(&self) {
+        self.token.lock().await.invalidate();
+    }
+
+    /// Feed an event received from the server into the auth helper
+    pub async fn handle_event(&self, event: &EventBody) {
+        if let EventBody::Login(login) = event {
+            self.token.lock().await.handle_login(login);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn login(is_logged_in: bool, login_reason_code: LoginReasonCode) -> Login {
+        Login {
+            is_logged_in,
+            user_index: 0,
+            login_reason_code,
+        }
+    }
+
+    fn cached(now: Instant) -> TokenCache {
+        let mut cache = TokenCache::default();
+        cache.store("token".to_string(), Duration::from_secs(60), now);
+        cache
+    }
+
+    #[test]
+    fn test_token_expiry() {
+        let now = Instant::now();
+        let cache = cached(now);
+
+        assert_eq!(
+            cache
+                .get(now + Duration::from_secs(59))
+                .map(|token| token.value),
+            Some("token".to_string())
+        );
+        assert!(cache.get(now + Duration::from_secs(60)).is_none());
+    }
+
+    #[test]
+    fn test_token_invalidate() {
+        let now = Instant::now();
+        let mut cache = cached(now);
+
+        cache.invalidate();
+        assert!(cache.get(now).is_none());
+    }
+
+    #[test]
+    fn test_token_dropped_on_login() {
+        let now = Instant::now();
+
+        let mut cache = cached(now);
+        cache.handle_login(&login(false, LoginReasonCode::UserInitiated));
+        assert!(cache.get(now).is_none());
+
+        let mut cache = cached(now);
+        cache.handle_login(&login(true, LoginReasonCode::AccesstokenRefreshError));
+        assert!(cache.get(now).is_none());
+    }
+}
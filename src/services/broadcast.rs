@@ -0,0 +1,119 @@
+use std::{sync::Arc, time::Duration};
+
+use tokio::sync::watch;
+use tracing::{debug, warn};
+
+use crate::{
+    protocol::{
+        broadcast::{BroadcastStart, BroadcastState, BroadcastStop, GetBroadcastStatus},
+        EventBody,
+    },
+    sdk::{OriginSdk, SdkError, SdkResult},
+};
+
+/// Broadcasting (streaming) controller
+///
+/// Tracks the broadcast lifecycle from [`EventBody::BroadcastEvent`] events, starting
+/// with the state reported by [`GetBroadcastStatus`].
+pub struct BroadcastController {
+    sdk: Arc<OriginSdk>,
+    state: watch::Sender<Option<BroadcastState>>,
+}
+
+impl BroadcastController {
+    /// Create the controller and load the current broadcast state
+    pub async fn new(sdk: Arc<OriginSdk>) -> SdkResult<Self> {
+        let (state, _) = watch::channel(None);
+        let controller = Self { sdk, state };
+
+        controller.refresh().await?;
+        Ok(controller)
+    }
+
+    /// Query the broadcast state from the server
+    pub async fn refresh(&self) -> SdkResult<Option<BroadcastState>> {
+        let response = self.sdk.request(GetBroadcastStatus).await?;
+        let state = response.state();
+
+        if state.is_none() {
+            warn!("Unknown broadcast status {}", response.status);
+        }
+
+        self.state.send_replace(state);
+        Ok(state)
+    }
+
+    /// Last known broadcast state, `None` until one is known
+    pub fn state(&self) -> Option<BroadcastState> {
+        *self.state.borrow()
+    }
+
+    /// Subscribe to broadcast state changes
+    pub fn subscribe(&self) -> watch::Receiver<Option<BroadcastState>> {
+        self.state.subscribe()
+    }
+
+    /// Returns true if the game is being broadcast
+    pub fn is_broadcasting(&self) -> bool {
+        self.state() == Some(BroadcastState::Started)
+    }
+
+    /// Start broadcasting and wait until the broadcast starts
+    ///
+    /// The client may show dialogs to set up the broadcast first, so `timeout` should
+    /// leave the user enough time. Fails if the broadcast is blocked, reports an error
+    /// or does not start within `timeout`.
+    pub async fn start(&self, timeout: Duration) -> SdkResult<()> {
+        if self.is_broadcasting() {
+            return Ok(());
+        }
+
+        let mut state = self.state.subscribe();
+        state.mark_unchanged();
+
+        self.sdk.request(BroadcastStart).await?;
+
+        let outcome = tokio::time::timeout(timeout, wait_outcome(&mut state))
+            .await
+            .map_err(|_| SdkError::Other("Broadcast did not start in time".to_string()))??;
+
+        match outcome {
+            BroadcastState::Started => Ok(()),
+            BroadcastState::Blocked => Err(SdkError::Other("Broadcast was blocked".to_string())),
+            _ => Err(SdkError::Other("Broadcast failed to start".to_string())),
+        }
+    }
+
+    /// Stop broadcasting
+    pub async fn stop(&self) -> SdkResult<()> {
+        self.sdk.request(BroadcastStop).await?;
+        Ok(())
+    }
+
+    /// Feed an event received from the server into the controller
+    pub async fn handle_event(&self, event: &EventBody) {
+        if let EventBody::BroadcastEvent(event) = event {
+            debug!("Broadcast is {:?}", event.state);
+            self.state.send_replace(Some(event.state));
+        }
+    }
+}
+
+/// Wait until the broadcast starts, is blocked or fails
+async fn wait_outcome(
+    state: &mut watch::Receiver<Option<BroadcastState>>,
+) -> SdkResult<BroadcastState> {
+    loop {
+        state
+            .changed()
+            .await
+            .map_err(|_| SdkError::Other("Broadcast controller closed".to_string()))?;
+
+        if let Some(
+            outcome @ (BroadcastState::Started | BroadcastState::Blocked | BroadcastState::Error),
+        ) = *state.borrow_and_update()
+        {
+            return Ok(outcome);
+        }
+    }
+}
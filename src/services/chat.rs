@@ -0,0 +1,427 @@
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use tokio::{
+    sync::{broadcast, Mutex},
+    task::JoinHandle,
+};
+
+use crate::{
+    protocol::{
+        chat::{ChatMessageEvent, ChatState, SendChatMessage},
+        EventBody,
+    },
+    sdk::{OriginSdk, SdkResult},
+    services::current_user_ids,
+};
+
+/// Time after which a typing indicator is dropped if no `USER_WRITING_END` arrives
+pub const DEFAULT_TYPING_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Number of messages kept per conversation
+const MAX_HISTORY: usize = 200;
+
+/// Who a conversation is held with
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Conversation {
+    /// One-to-one conversation with a user
+    Direct(u64),
+    /// Conversation in a group chat
+    Group(String),
+}
+
+impl Conversation {
+    fn of(event: &ChatMessageEvent) -> Self {
+        if event.group_id.is_empty() {
+            Conversation::Direct(event.from_id)
+        } else {
+            Conversation::Group(event.group_id.clone())
+        }
+    }
+}
+
+/// A message sent or received in a conversation
+#[derive(Debug, Clone)]
+pub struct ChatMessage {
+    pub from_id: u64,
+    pub message: String,
+    pub timestamp: SystemTime,
+}
+
+/// Snapshot of a conversation
+#[derive(Debug, Clone)]
+pub struct ChatThread {
+    pub conversation: Conversation,
+    /// Thread id sent along with every message of the conversation
+    pub thread_id: String,
+    pub messages: Vec<ChatMessage>,
+    /// Participants currently writing a message
+    pub typing: Vec<u64>,
+}
+
+/// Change published to [`ChatSessions::updates`] subscribers
+#[derive(Debug, Clone)]
+pub enum ChatUpdate {
+    Message {
+        conversation: Conversation,
+        message: ChatMessage,
+    },
+    Typing {
+        user_id: u64,
+        typing: bool,
+    },
+}
+
+#[derive(Debug)]
+struct ThreadState {
+    thread_id: String,
+    participants: HashSet<u64>,
+    messages: Vec<ChatMessage>,
+}
+
+impl ThreadState {
+    fn push(&mut self, message: ChatMessage) {
+        self.participants.insert(message.from_id);
+        self.messages.push(message);
+
+        if self.messages.len() > MAX_HISTORY {
+            let overflow = self.messages.len() - MAX_HISTORY;
+            self.messages.drain(..overflow);
+        }
+    }
+}
+
+/// Chat conversations of the current user
+///
+/// Groups [`EventBody::ChatMessageEvent`]s into per-conversation [`ChatThread`]s with
+/// their message history and tracks [`EventBody::ChatStateUpdateEvent`] typing
+/// indicators. An indicator expires after `typing_timeout` if no end state is
+/// received, which is published as [`ChatUpdate::Typing`] like an explicit end. The
+/// expiry task stops when the sessions are dropped.
+///
+/// LSX has no request to publish the chat state of the current user, so typing
+/// indicators are only tracked for incoming messages.
+pub struct ChatSessions {
+    sdk: Arc<OriginSdk>,
+    user_id: u64,
+    typing_timeout: Duration,
+    threads: Mutex<HashMap<Conversation, ThreadState>>,
+    typing: Arc<Mutex<HashMap<u64, Instant>>>,
+    updates: broadcast::Sender<ChatUpdate>,
+    expiry: JoinHandle<()>,
+}
+
+impl Drop for ChatSessions {
+    fn drop(&mut self) {
+        self.expiry.abort();
+    }
+}
+
+impl ChatSessions {
+    /// Create the chat sessions of the current user
+    pub async fn new(sdk: Arc<OriginSdk>, typing_timeout: Duration) -> SdkResult<Self> {
+        let (user_id, _) = current_user_ids(&sdk).await?;
+        let (updates, _) = broadcast::channel(64);
+        let typing = Arc::new(Mutex::new(HashMap::new()));
+
+        let expiry = tokio::spawn(Self::expire_typing(
+            typing.clone(),
+            updates.clone(),
+            typing_timeout,
+        ));
+
+        Ok(Self {
+            sdk,
+            user_id,
+            typing_timeout,
+            threads: Mutex::new(HashMap::new()),
+            typing,
+            updates,
+            expiry,
+        })
+    }
+
+    /// Subscribe to incoming messages and typing changes
+    pub fn updates(&self) -> broadcast::Receiver<ChatUpdate> {
+        self.updates.subscribe()
+    }
+
+    /// Snapshot of a single conversation
+    pub async fn thread(&self, conversation: &Conversation) -> Option<ChatThread> {
+        let threads = self.threads.lock().await;
+        let state = threads.get(conversation)?;
+        Some(self.snapshot(conversation, state).await)
+    }
+
+    /// Snapshot of every known conversation
+    pub async fn threads(&self) -> Vec<ChatThread> {
+        let threads = self.threads.lock().await;
+        let mut snapshots = Vec::with_capacity(threads.len());
+
+        for (conversation, state) in threads.iter() {
+            snapshots.push(self.snapshot(conversation, state).await);
+        }
+
+        snapshots
+    }
+
+    /// Returns true if the user started writing and neither finished nor timed out
+    pub async fn is_typing(&self, user_id: u64) -> bool {
+        self.typing
+            .lock()
+            .await
+            .get(&user_id)
+            .is_some_and(|since| since.elapsed() < self.typing_timeout)
+    }
+
+    /// Send a message to a conversation, starting a new thread if needed
+    pub async fn send(
+        &self,
+        conversation: Conversation,
+        message: impl Into<String>,
+    ) -> SdkResult<()> {
+        let message = message.into();
+        let thread_id = self.thread_id(&conversation).await;
+
+        let (to_id, group_id) = match &conversation {
+            Conversation::Direct(user_id) => (*user_id, String::new()),
+            Conversation::Group(group_id) => (0, group_id.clone()),
+        };
+
+        self.sdk
+            .request(SendChatMessage {
+                from_id: self.user_id,
+                to_id,
+                thread: thread_id,
+                message: message.clone(),
+                group_id,
+            })
+            .await?;
+
+        let mut threads = self.threads.lock().await;
+        if let Some(state) = threads.get_mut(&conversation) {
+            state.push(ChatMessage {
+                from_id: self.user_id,
+                message,
+                timestamp: SystemTime::now(),
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Feed an event received from the server into the chat sessions
+    pub async fn handle_event(&self, event: &EventBody) {
+        match event {
+            EventBody::ChatMessageEvent(event) => {
+                let conversation = Conversation::of(event);
+                let message = ChatMessage {
+                    from_id: event.from_id,
+                    message: event.message.clone(),
+                    timestamp: SystemTime::now(),
+                };
+
+                {
+                    let mut threads = self.threads.lock().await;
+                    let state =
+                        threads
+                            .entry(conversation.clone())
+                            .or_insert_with(|| ThreadState {
+                                thread_id: event.thread.clone(),
+                                participants: HashSet::new(),
+                                messages: Vec::new(),
+                            });
+
+                    if !event.thread.is_empty() {
+                        state.thread_id = event.thread.clone();
+                    }
+
+                    state.push(message.clone());
+                }
+
+                // A received message implicitly ends the typing state of its sender
+                self.set_typing(event.from_id, false).await;
+
+                let _ = self.updates.send(ChatUpdate::Message {
+                    conversation,
+                    message,
+                });
+            }
+            EventBody::ChatStateUpdateEvent(event) => {
+                let typing = matches!(event.state, ChatState::UserWritingStart);
+                self.set_typing(event.user_id, typing).await;
+            }
+            _ => {}
+        }
+    }
+
+    async fn set_typing(&self, user_id: u64, typing: bool) {
+        let changed = {
+            let mut states = self.typing.lock().await;
+            if typing {
+                states.insert(user_id, Instant::now()).is_none()
+            } else {
+                states.remove(&user_id).is_some()
+            }
+        };
+
+        if changed {
+            let _ = self.updates.send(ChatUpdate::Typing { user_id, typing });
+        }
+    }
+
+    /// Drop typing indicators that timed out and publish their end
+    async fn expire_typing(
+        typing: Arc<Mutex<HashMap<u64, Instant>>>,
+        updates: broadcast::Sender<ChatUpdate>,
+        timeout: Duration,
+    ) {
+        // Expire indicators at most a quarter of the timeout late
+        let mut ticker = tokio::time::interval((timeout / 4).max(Duration::from_millis(100)));
+
+        loop {
+            ticker.tick().await;
+
+            let expired = expire(&mut *typing.lock().await, timeout, Instant::now());
+            for user_id in expired {
+                let _ = updates.send(ChatUpdate::Typing {
+                    user_id,
+                    typing: false,
+                });
+            }
+        }
+    }
+
+    /// Thread id of a conversation, generating one for new conversations
+    async fn thread_id(&self, conversation: &Conversation) -> String {
+        let mut threads = self.threads.lock().await;
+        let state = threads.entry(conversation.clone()).or_insert_with(|| {
+            let nanos = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_nanos();
+
+            let participants = match conversation {
+                Conversation::Direct(user_id) => HashSet::from([*user_id]),
+                Conversation::Group(_) => HashSet::new(),
+            };
+
+            ThreadState {
+                thread_id: format!("{}-{:x}", self.user_id, nanos),
+                participants,
+                messages: Vec::new(),
+            }
+        });
+
+        state.thread_id.clone()
+    }
+
+    async fn snapshot(&self, conversation: &Conversation, state: &ThreadState) -> ChatThread {
+        let mut typing = Vec::new();
+        for user_id in &state.participants {
+            if *user_id != self.user_id && self.is_typing(*user_id).await {
+                typing.push(*user_id);
+            }
+        }
+
+        ChatThread {
+            conversation: conversation.clone(),
+            thread_id: state.thread_id.clone(),
+            messages: state.messages.clone(),
+            typing,
+        }
+    }
+}
+
+/// Remove the typing indicators older than `timeout` and return their users
+fn expire(typing: &mut HashMap<u64, Instant>, timeout: Duration, now: Instant) -> Vec<u64> {
+    let expired: Vec<u64> = typing
+        .iter()
+        .filter(|(_, since)| now.duration_since(**since) >= timeout)
+        .map(|(user_id, _)| *user_id)
+        .collect();
+
+    for user_id in &expired {
+        typing.remove(user_id);
+    }
+
+    expired
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(from_id: u64, group_id: &str) -> ChatMessageEvent {
+        ChatMessageEvent {
+            from_id,
+            group_id: group_id.to_string(),
+            thread: String::new(),
+            message: "hi".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_conversation_of() {
+        assert_eq!(Conversation::of(&event(7, "")), Conversation::Direct(7));
+        assert_eq!(
+            Conversation::of(&event(7, "squad")),
+            Conversation::Group("squad".to_string())
+        );
+        // Group messages from different senders share one conversation
+        assert_eq!(
+            Conversation::of(&event(7, "squad")),
+            Conversation::of(&event(8, "squad"))
+        );
+    }
+
+    #[test]
+    fn test_history_trim() {
+        let mut state = ThreadState {
+            thread_id: String::new(),
+            participants: HashSet::new(),
+            messages: Vec::new(),
+        };
+
+        for i in 0..MAX_HISTORY + 5 {
+            state.push(ChatMessage {
+                from_id: (i % 2) as u64,
+                message: i.to_string(),
+                timestamp: SystemTime::now(),
+            });
+        }
+
+        assert_eq!(state.messages.len(), MAX_HISTORY);
+        assert_eq!(state.messages[0].message, "5");
+        assert_eq!(
+            state
+                .messages
+                .last()
+                .map(|message| message.message.as_str()),
+            Some((MAX_HISTORY + 4).to_string().as_str())
+        );
+        assert_eq!(state.participants, HashSet::from([0, 1]));
+    }
+
+    #[test]
+    fn test_typing_expiry() {
+        let now = Instant::now();
+        let timeout = Duration::from_secs(10);
+        let mut typing = HashMap::from([
+            (1, now),
+            (2, now + Duration::from_secs(5)),
+            (3, now + Duration::from_secs(8)),
+        ]);
+
+        assert!(expire(&mut typing, timeout, now + Duration::from_secs(9)).is_empty());
+
+        let mut expired = expire(&mut typing, timeout, now + Duration::from_secs(15));
+        expired.sort();
+        assert_eq!(expired, [1, 2]);
+        assert_eq!(typing.len(), 1);
+        assert!(typing.contains_key(&3));
+    }
+}
@@ -0,0 +1,396 @@
+use std::{collections::HashMap, sync::Arc, time::Duration};
+
+use tokio::sync::{watch, Mutex};
+use tracing::debug;
+
+use crate::{
+    protocol::{
+        chunk::{
+            AreChunksInstalled, ChunkState, ChunkStatus, CreateChunk, GetChunkPriority,
+            IsFileDownloaded, IsProgressiveInstallationAvailable, QueryChunkFiles,
+            QueryChunkStatus, SetChunkPriority,
+        },
+        EventBody,
+    },
+    sdk::{OriginSdk, SdkError, SdkResult},
+};
+
+/// Progressive installation tracker for a single item
+///
+/// Keeps the state, progress and ETA of every chunk up to date from
+/// [`EventBody::ChunkStatus`] events, lets the game wait for a chunk to be
+/// installed and answers whether a file can be used yet from a cached map of
+/// chunks to files.
+pub struct ChunkTracker {
+    sdk: Arc<OriginSdk>,
+    item_id: String,
+    chunks: watch::Sender<HashMap<i32, ChunkStatus>>,
+    files: Mutex<HashMap<i32, Vec<String>>>,
+}
+
+impl ChunkTracker {
+    /// Create a tracker for an item and load the status of its chunks
+    pub async fn new(sdk: Arc<OriginSdk>, item_id: impl Into<String>) -> SdkResult<Self> {
+        let (chunks, _) = watch::channel(HashMap::new());
+
+        let tracker = Self {
+            sdk,
+            item_id: item_id.into(),
+            chunks,
+            files: Mutex::new(HashMap::new()),
+        };
+
+        tracker.refresh().await?;
+        Ok(tracker)
+    }
+
+    /// Query the status of every chunk from the server
+    pub async fn refresh(&self) -> SdkResult<()> {
+        let response = self
+            .sdk
+            .request(QueryChunkStatus {
+                item_id: self.item_id.clone(),
+            })
+            .await?;
+
+        self.chunks.send_modify(|chunks| {
+            for status in response.chunk_status {
+                chunks.insert(status.chunk_id, status);
+            }
+        });
+
+        Ok(())
+    }
+
+    /// Returns true if the item can be played before it is fully installed
+    pub async fn is_available(&self) -> SdkResult<bool> {
+        let response = self
+            .sdk
+            .request(IsProgressiveInstallationAvailable {
+                item_id: self.item_id.clone(),
+            })
+            .await?;
+
+        Ok(response.available)
+    }
+
+    /// Subscribe to chunk status changes
+    pub fn subscribe(&self) -> watch::Receiver<HashMap<i32, ChunkStatus>> {
+        self.chunks.subscribe()
+    }
+
+    /// Last known status of a chunk
+    pub fn status(&self, chunk_id: i32) -> Option<ChunkStatus> {
+        self.chunks.borrow().get(&chunk_id).cloned()
+    }
+
+    /// Last known status of every chunk
+    pub fn chunks(&self) -> Vec<ChunkStatus> {
+        self.chunks.borrow().values().cloned().collect()
+    }
+
+    /// Installation progress of the whole item between 0.0 and 1.0, weighted by chunk size
+    pub fn progress(&self) -> f32 {
+        item_progress(&self.chunks.borrow())
+    }
+
+    /// Estimated time until every chunk is installed, as last reported by the server
+    pub fn eta(&self) -> Option<Duration> {
+        self.chunks
+            .borrow()
+            .values()
+            .map(|chunk| chunk.total_eta)
+            .filter(|eta| *eta >= 0)
+            .max()
+            .map(|eta| Duration::from_secs(eta as u64))
+    }
+
+    /// Wait until a chunk is installed
+    ///
+    /// Fails if the chunk reports [`ChunkState::Error`], or if the server does not
+    /// know the chunk even after a [`ChunkTracker::refresh`].
+    pub async fn wait_installed(&self, chunk_id: i32) -> SdkResult<ChunkStatus> {
+        if self.status(chunk_id).is_none() {
+            self.refresh().await?;
+        }
+
+        let mut chunks = self.chunks.subscribe();
+
+        loop {
+            let status = chunks.borrow_and_update().get(&chunk_id).cloned();
+            if let Some(result) = install_outcome(&self.item_id, chunk_id, status) {
+                return result;
+            }
+
+            chunks
+                .changed()
+                .await
+                .map_err(|_| SdkError::Other("Chunk tracker closed".to_string()))?;
+        }
+    }
+
+    /// Ask the server whether all given chunks are installed
+    pub async fn are_installed(&self, chunk_ids: Vec<i32>) -> SdkResult<bool> {
+        let response = self
+            .sdk
+            .request(AreChunksInstalled {
+                item_id: self.item_id.clone(),
+                chunk_ids,
+            })
+            .await?;
+
+        Ok(response.installed)
+    }
+
+    /// Current download order of the chunks
+    pub async fn priority(&self) -> SdkResult<Vec<i32>> {
+        let response = self
+            .sdk
+            .request(GetChunkPriority {
+                item_id: self.item_id.clone(),
+            })
+            .await?;
+
+        Ok(response.chunk_ids)
+    }
+
+    /// Replace the download order of the chunks
+    pub async fn set_priority(&self, chunk_ids: Vec<i32>) -> SdkResult<()> {
+        self.sdk
+            .request(SetChunkPriority {
+                item_id: self.item_id.clone(),
+                chunk_ids,
+            })
+            .await?;
+
+        Ok(())
+    }
+
+    /// Move chunks to the front of the download order, keeping the order of the rest
+    pub async fn prioritize(&self, chunk_ids: &[i32]) -> SdkResult<()> {
+        let current = self.priority().await?;
+
+        let mut order = chunk_ids.to_vec();
+        order.extend(current.into_iter().filter(|id| !chunk_ids.contains(id)));
+
+        self.set_priority(order).await
+    }
+
+    /// Create an on-demand chunk out of a list of files
+    pub async fn create_chunk(&self, files: Vec<String>) -> SdkResult<i32> {
+        let response = self
+            .sdk
+            .request(CreateChunk {
+                item_id: self.item_id.clone(),
+                files: files.clone(),
+            })
+            .await?;
+
+        self.files.lock().await.insert(response.chunk_id, files);
+        Ok(response.chunk_id)
+    }
+
+    /// Files contained in a chunk, queried once and cached
+    pub async fn files(&self, chunk_id: i32) -> SdkResult<Vec<String>> {
+        if let Some(files) = self.files.lock().await.get(&chunk_id) {
+            return Ok(files.clone());
+        }
+
+        let response = self
+            .sdk
+            .request(QueryChunkFiles {
+                item_id: self.item_id.clone(),
+                chunk_id,
+            })
+            .await?;
+
+        self.files
+            .lock()
+            .await
+            .insert(chunk_id, response.files.clone());
+
+        Ok(response.files)
+    }
+
+    /// Returns true if a file can be used, i.e. the chunk containing it is installed
+    ///
+    /// Files that are not part of any known chunk are checked with [`IsFileDownloaded`].
+    pub async fn is_file_playable(&self, path: &str) -> SdkResult<bool> {
+        let wanted = normalize_path(path);
+        let chunk_ids: Vec<i32> = self.chunks.borrow().keys().copied().collect();
+
+        for chunk_id in chunk_ids {
+            let files = self.files(chunk_id).await?;
+            if files.iter().any(|file| normalize_path(file) == wanted) {
+                let installed = self
+                    .status(chunk_id)
+                    .is_some_and(|status| status.state == ChunkState::Installed);
+
+                return Ok(installed);
+            }
+        }
+
+        let response = self
+            .sdk
+            .request(IsFileDownloaded {
+                item_id: self.item_id.clone(),
+                filepath: path.to_string(),
+            })
+            .await?;
+
+        Ok(response.downloaded)
+    }
+
+    /// Feed an event received from the server into the tracker
+    pub async fn handle_event(&self, event: &EventBody) {
+        if let EventBody::ChunkStatus(status) = event {
+            if status.item_id != self.item_id {
+                return;
+            }
+
+            debug!(
+                "Chunk {} is {:?} ({:.0}%)",
+                status.chunk_id,
+                status.state,
+                chunk_progress(status) * 100.0
+            );
+
+            self.chunks.send_modify(|chunks| {
+                chunks.insert(status.chunk_id, status.clone());
+            });
+        }
+    }
+}
+
+/// Result of waiting for a chunk given its last known status, `None` to keep waiting
+fn install_outcome(
+    item_id: &str,
+    chunk_id: i32,
+    status: Option<ChunkStatus>,
+) -> Option<SdkResult<ChunkStatus>> {
+    let Some(status) = status else {
+        return Some(Err(SdkError::Other(format!(
+            "Chunk {} of {} is unknown",
+            chunk_id, item_id
+        ))));
+    };
+
+    match status.state {
+        ChunkState::Installed => Some(Ok(status)),
+        ChunkState::Error => Some(Err(SdkError::Other(format!(
+            "Chunk {} of {} failed to install",
+            chunk_id, item_id
+        )))),
+        _ => None,
+    }
+}
+
+/// Installation progress of every chunk between 0.0 and 1.0, weighted by chunk size
+fn item_progress(chunks: &HashMap<i32, ChunkStatus>) -> f32 {
+    let total: u64 = chunks.values().map(|chunk| chunk.size).sum();
+
+    if total == 0 {
+        return 0.0;
+    }
+
+    let done: f64 = chunks
+        .values()
+        .map(|chunk| chunk.size as f64 * chunk_progress(chunk) as f64)
+        .sum();
+
+    (done / total as f64) as f32
+}
+
+/// Progress of a single chunk between 0.0 and 1.0
+///
+/// Progress is reported as a fraction, like the content progress in
+/// [`ContentMonitor`](crate::services::content::ContentMonitor).
+fn chunk_progress(chunk: &ChunkStatus) -> f32 {
+    match chunk.state {
+        ChunkState::Installed => 1.0,
+        _ => chunk.progress.clamp(0.0, 1.0),
+    }
+}
+
+/// Normalize a path for comparison, the client reports Windows paths
+fn normalize_path(path: &str) -> String {
+    path.trim_start_matches(['/', '\\'])
+        .replace('\\', "/")
+        .to_lowercase()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::chunk::ChunkType;
+
+    fn chunk(chunk_id: i32, state: ChunkState, progress: f32, size: u64) -> ChunkStatus {
+        ChunkStatus {
+            chunk_id,
+            name: format!("chunk{}", chunk_id),
+            item_id: "item".to_string(),
+            r#type: ChunkType::Normal,
+            state,
+            progress,
+            size,
+            chunk_eta: -1,
+            total_eta: -1,
+        }
+    }
+
+    #[test]
+    fn test_chunk_progress() {
+        assert_eq!(
+            chunk_progress(&chunk(1, ChunkState::Downloading, 0.5, 1)),
+            0.5
+        );
+        // Out of range values are clamped, not read as a percentage
+        assert_eq!(
+            chunk_progress(&chunk(1, ChunkState::Downloading, 50.0, 1)),
+            1.0
+        );
+        assert_eq!(
+            chunk_progress(&chunk(1, ChunkState::Downloading, -1.0, 1)),
+            0.0
+        );
+        assert_eq!(
+            chunk_progress(&chunk(1, ChunkState::Installed, 0.0, 1)),
+            1.0
+        );
+    }
+
+    #[test]
+    fn test_item_progress() {
+        assert_eq!(item_progress(&HashMap::new()), 0.0);
+
+        let chunks = HashMap::from([
+            (1, chunk(1, ChunkState::Installed, 0.0, 300)),
+            (2, chunk(2, ChunkState::Downloading, 0.5, 200)),
+            (3, chunk(3, ChunkState::Queued, 0.0, 500)),
+        ]);
+
+        assert_eq!(item_progress(&chunks), 0.4);
+    }
+
+    #[test]
+    fn test_install_outcome() {
+        assert!(matches!(install_outcome("item", 1, None), Some(Err(_))));
+        assert!(matches!(
+            install_outcome("item", 1, Some(chunk(1, ChunkState::Error, 0.0, 1))),
+            Some(Err(_))
+        ));
+        assert!(matches!(
+            install_outcome("item", 1, Some(chunk(1, ChunkState::Installed, 1.0, 1))),
+            Some(Ok(status)) if status.chunk_id == 1
+        ));
+        assert!(
+            install_outcome("item", 1, Some(chunk(1, ChunkState::Downloading, 0.5, 1))).is_none()
+        );
+    }
+
+    #[test]
+    fn test_normalize_path() {
+        assert_eq!(normalize_path("\\Data\\Level1.pak"), "data/level1.pak");
+        assert_eq!(normalize_path("/data/level1.pak"), "data/level1.pak");
+    }
+}
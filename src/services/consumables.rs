@@ -0,0 +1,402 @@
+use std::{collections::HashMap, sync::Arc};
+
+use tokio::sync::Mutex;
+use tracing::{info, warn};
+
+use crate::{
+    protocol::entitlements::Entitlement,
+    sdk::{SdkError, SdkResult},
+    services::entitlements::{EntitlementKey, EntitlementManager},
+};
+
+/// Outcome of a consumption
+#[derive(Debug, Clone)]
+pub struct Consumption {
+    /// State of the entitlement after the consumption
+    pub entitlement: Entitlement,
+    /// True if no request was sent because an earlier attempt whose response was
+    /// lost turned out to have been applied
+    pub recovered: bool,
+}
+
+/// State of an entitlement right before a consumption request
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Snapshot {
+    use_count: i32,
+    uses: i32,
+}
+
+/// Consumption state of a single entitlement
+#[derive(Debug, Default)]
+struct Slot {
+    /// A consumption or reconciliation of the entitlement is in progress
+    in_flight: bool,
+    /// State before a consumption request whose outcome is unknown
+    unconfirmed: Option<Snapshot>,
+}
+
+/// Consumption state of every entitlement that has one
+#[derive(Debug, Default)]
+struct Ledger {
+    slots: HashMap<EntitlementKey, Slot>,
+}
+
+impl Ledger {
+    /// Claim an entitlement, returns `None` if it is already claimed, otherwise the
+    /// state recorded before a consumption with an unknown outcome, if any
+    fn begin(&mut self, key: &EntitlementKey) -> Option<Option<Snapshot>> {
+        let slot = self.slots.entry(key.clone()).or_default();
+        if slot.in_flight {
+            return None;
+        }
+
+        slot.in_flight = true;
+        Some(slot.unconfirmed)
+    }
+
+    /// Record the state before sending a consumption request
+    fn record(&mut self, key: &EntitlementKey, snapshot: Snapshot) {
+        self.slots.entry(key.clone()).or_default().unconfirmed = Some(snapshot);
+    }
+
+    /// Forget the recorded state once the outcome of the request is known
+    fn resolve(&mut self, key: &EntitlementKey) {
+        if let Some(slot) = self.slots.get_mut(key) {
+            slot.unconfirmed = None;
+        }
+    }
+
+    /// Release an entitlement claimed with [`Ledger::begin`]
+    fn finish(&mut self, key: &EntitlementKey) {
+        if let Some(slot) = self.slots.get_mut(key) {
+            slot.in_flight = false;
+            if slot.unconfirmed.is_none() {
+                self.slots.remove(key);
+            }
+        }
+    }
+
+    fn unconfirmed(&self) -> Vec<EntitlementKey> {
+        self.slots
+            .iter()
+            .filter(|(_, slot)| !slot.in_flight && slot.unconfirmed.is_some())
+            .map(|(key, _)| key.clone())
+            .collect()
+    }
+}
+
+/// What to do with a consumption given the current server state
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Step {
+    /// An earlier request with a lost response was applied, do not consume again
+    Recovered,
+    /// Not enough uses remaining
+    Insufficient,
+    /// Send the consumption request
+    Consume,
+}
+
+fn next_step(
+    previous: Option<&Snapshot>,
+    current: &Entitlement,
+    uses: i32,
+    allow_overuse: bool,
+) -> Step {
+    if previous.is_some_and(|previous| was_applied(previous, current)) {
+        Step::Recovered
+    } else if !allow_overuse && current.use_count < uses {
+        Step::Insufficient
+    } else {
+        Step::Consume
+    }
+}
+
+/// Consumable entitlements with protection against double consumption
+///
+/// Only one consumption per entitlement runs at a time, concurrent calls fail right
+/// away. A consumption request that times out may or may not have been applied by
+/// the server. Instead of retrying blindly, the entitlement is queried again before
+/// the next attempt and its `use_count` is compared against the value from before
+/// the lost request, so a retry never consumes twice.
+pub struct Consumables {
+    entitlements: Arc<EntitlementManager>,
+    ledger: Mutex<Ledger>,
+}
+
+impl Consumables {
+    /// Create the helper on top of the entitlement cache of the current user
+    pub fn new(entitlements: Arc<EntitlementManager>) -> Self {
+        Self {
+            entitlements,
+            ledger: Mutex::new(Ledger::default()),
+        }
+    }
+
+    /// Entitlements with a consumption whose outcome is still unknown
+    pub async fn unconfirmed(&self) -> Vec<EntitlementKey> {
+        self.ledger.lock().await.unconfirmed()
+    }
+
+    /// Consume uses of an entitlement
+    ///
+    /// Fails without sending a request if the entitlement has fewer remaining uses
+    /// than requested, unless `allow_overuse` is set, or if another consumption of
+    /// the same entitlement is in progress.
+    pub async fn consume(
+        &self,
+        item_id: &str,
+        tag: &str,
+        uses: i32,
+        allow_overuse: bool,
+    ) -> SdkResult<Consumption> {
+        let key = (item_id.to_string(), tag.to_string());
+
+        let Some(previous) = self.ledger.lock().await.begin(&key) else {
+            return Err(SdkError::Other(format!(
+                "Consumption of {} ({}) is already in progress",
+                item_id, tag
+            )));
+        };
+
+        let result = self
+            .consume_checked(&key, previous, uses, allow_overuse)
+            .await;
+
+        self.ledger.lock().await.finish(&key);
+        result
+    }
+
+    /// Check whether consumptions with a lost response were applied, without
+    /// consuming anything. Returns the entitlements that were confirmed.
+    pub async fn reconcile(&self) -> SdkResult<Vec<Entitlement>> {
+        let mut confirmed = Vec::new();
+
+        for key in self.unconfirmed().await {
+            let previous = {
+                let mut ledger = self.ledger.lock().await;
+                match ledger.begin(&key) {
+                    Some(Some(previous)) => previous,
+                    // Resolved since
+                    Some(None) => {
+                        ledger.finish(&key);
+                        continue;
+                    }
+                    // Claimed by a consumption in the meantime
+                    None => continue,
+                }
+            };
+
+            let result = self.fetch(&key).await;
+
+            let mut ledger = self.ledger.lock().await;
+            match result {
+                Ok(current) => {
+                    if was_applied(&previous, &current) {
+                        confirmed.push(current);
+                    } else {
+                        warn!("Lost consumption of {} ({}) was not applied", key.0, key.1);
+                    }
+
+                    ledger.resolve(&key);
+                    ledger.finish(&key);
+                }
+                Err(err) => {
+                    ledger.finish(&key);
+                    return Err(err);
+                }
+            }
+        }
+
+        Ok(confirmed)
+    }
+
+    async fn consume_checked(
+        &self,
+        key: &EntitlementKey,
+        previous: Option<Snapshot>,
+        uses: i32,
+        allow_overuse: bool,
+    ) -> SdkResult<Consumption> {
+        // Always start from the server state, the cache may be outdated
+        let entitlement = self.fetch(key).await?;
+
+        // The earlier outcome is known now, either way
+        self.ledger.lock().await.resolve(key);
+
+        match next_step(previous.as_ref(), &entitlement, uses, allow_overuse) {
+            Step::Recovered => {
+                info!(
+                    "Earlier consumption of {} ({}) was applied, not consuming again",
+                    key.0, key.1
+                );
+
+                return Ok(Consumption {
+                    entitlement,
+                    recovered: true,
+                });
+            }
+            Step::Insufficient => {
+                return Err(SdkError::Other(format!(
+                    "Cannot consume {} uses of {} ({}), {} remaining",
+                    uses, key.0, key.1, entitlement.use_count
+                )));
+            }
+            Step::Consume => {}
+        }
+
+        self.ledger.lock().await.record(
+            key,
+            Snapshot {
+                use_count: entitlement.use_count,
+                uses,
+            },
+        );
+
+        let result = self
+            .entitlements
+            .consume(entitlement, uses, allow_overuse)
+            .await;
+
+        match result {
+            Err(err) if is_ambiguous(&err) => Err(err),
+            result => {
+                self.ledger.lock().await.resolve(key);
+
+                result.map(|entitlement| Consumption {
+                    entitlement,
+                    recovered: false,
+                })
+            }
+        }
+    }
+
+    async fn fetch(&self, key: &EntitlementKey) -> SdkResult<Entitlement> {
+        self.entitlements
+            .fetch(&key.0, &key.1)
+            .await?
+            .ok_or_else(|| {
+                SdkError::Other(format!("User has no entitlement {} ({})", key.0, key.1))
+            })
+    }
+}
+
+/// Returns true if the use count dropped by exactly the uses of the lost request
+///
+/// The version is not taken into account, it also changes when uses are granted.
+fn was_applied(previous: &Snapshot, current: &Entitlement) -> bool {
+    current.use_count == previous.use_count - previous.uses
+}
+
+/// Returns true if the request may have reached the server even though it failed
+fn is_ambiguous(err: &SdkError) -> bool {
+    !matches!(err, SdkError::OriginError(..))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key() -> EntitlementKey {
+        ("item".to_string(), "tag".to_string())
+    }
+
+    fn entitlement(use_count: i32, version: i32) -> Entitlement {
+        Entitlement {
+            type_: String::new(),
+            item_id: "item".to_string(),
+            entitlement_id: "1".to_string(),
+            entitlement_tag: "tag".to_string(),
+            group: String::new(),
+            resource_id: String::new(),
+            use_count,
+            expiration: String::new(),
+            grant_date: String::new(),
+            last_modified_date: String::new(),
+            version,
+        }
+    }
+
+    #[test]
+    fn test_was_applied() {
+        let previous = Snapshot {
+            use_count: 5,
+            uses: 2,
+        };
+
+        assert!(was_applied(&previous, &entitlement(3, 2)));
+        // The version alone does not tell
+        assert!(was_applied(&previous, &entitlement(3, 1)));
+        assert!(!was_applied(&previous, &entitlement(5, 1)));
+        // Uses granted in between
+        assert!(!was_applied(&previous, &entitlement(10, 2)));
+    }
+
+    #[test]
+    fn test_next_step() {
+        let previous = Snapshot {
+            use_count: 5,
+            uses: 2,
+        };
+
+        // Retry after a lost response that was applied
+        assert_eq!(
+            next_step(Some(&previous), &entitlement(3, 2), 2, false),
+            Step::Recovered
+        );
+        // Retry after a lost response that was not applied
+        assert_eq!(
+            next_step(Some(&previous), &entitlement(5, 1), 2, false),
+            Step::Consume
+        );
+        // A grant in between is not mistaken for the lost consumption
+        assert_eq!(
+            next_step(Some(&previous), &entitlement(10, 2), 2, false),
+            Step::Consume
+        );
+
+        assert_eq!(
+            next_step(None, &entitlement(1, 1), 2, false),
+            Step::Insufficient
+        );
+        assert_eq!(next_step(None, &entitlement(1, 1), 2, true), Step::Consume);
+    }
+
+    #[test]
+    fn test_ledger_guards_concurrent_consumption() {
+        let mut ledger = Ledger::default();
+
+        assert_eq!(ledger.begin(&key()), Some(None));
+        // Claimed before anything was recorded
+        assert_eq!(ledger.begin(&key()), None);
+
+        ledger.finish(&key());
+        assert!(ledger.slots.is_empty());
+        assert_eq!(ledger.begin(&key()), Some(None));
+    }
+
+    #[test]
+    fn test_ledger_keeps_unknown_outcome() {
+        let mut ledger = Ledger::default();
+        let snapshot = Snapshot {
+            use_count: 5,
+            uses: 1,
+        };
+
+        ledger.begin(&key());
+        ledger.record(&key(), snapshot);
+        // Still in flight, not reported as unconfirmed yet
+        assert!(ledger.unconfirmed().is_empty());
+
+        // The request timed out
+        ledger.finish(&key());
+        assert_eq!(ledger.unconfirmed(), vec![key()]);
+
+        // The next attempt or a reconciliation gets the recorded state
+        assert_eq!(ledger.begin(&key()), Some(Some(snapshot)));
+        assert!(ledger.unconfirmed().is_empty());
+
+        ledger.resolve(&key());
+        ledger.finish(&key());
+        assert!(ledger.slots.is_empty());
+    }
+}
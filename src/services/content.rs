@@ -0,0 +1,248 @@
+use std::{collections::HashMap, sync::Arc};
+
+use tokio::sync::{broadcast, watch, Mutex};
+use tracing::debug;
+
+use crate::{
+    protocol::{
+        chunk::StartDownload,
+        game::{ContentPhase, ContentState, Game, QueryContent},
+        EventBody,
+    },
+    sdk::{OriginSdk, SdkError, SdkResult},
+    services::current_user_ids,
+};
+
+/// Download or update progress of a single piece of content
+#[derive(Debug, Clone)]
+pub struct ContentProgress {
+    pub content_id: String,
+    pub display_name: String,
+    pub state: ContentState,
+    pub phase: ContentPhase,
+    /// Progress of the current state between 0.0 and 1.0
+    pub progress: f32,
+    pub installed_version: String,
+    pub available_version: String,
+}
+
+impl ContentProgress {
+    /// Returns true if a newer version than the installed one is available
+    pub fn update_available(&self) -> bool {
+        !self.available_version.is_empty()
+            && !self.installed_version.is_empty()
+            && self.available_version != self.installed_version
+    }
+}
+
+impl From<&Game> for ContentProgress {
+    fn from(game: &Game) -> Self {
+        Self {
+            content_id: game.content_id.clone(),
+            display_name: game.display_name.clone(),
+            state: game.state,
+            phase: game.state.phase(),
+            progress: game.progress_value.clamp(0.0, 1.0),
+            installed_version: game.installed_version.clone(),
+            available_version: game.available_version.clone(),
+        }
+    }
+}
+
+/// Download and update progress of the content owned by the current user
+///
+/// Loaded with [`QueryContent`] and updated from [`EventBody::CoreContentUpdated`]
+/// events. Every content id has its own progress stream, and all updates are also
+/// published to [`ContentMonitor::updates`] subscribers.
+pub struct ContentMonitor {
+    sdk: Arc<OriginSdk>,
+    user_id: u64,
+    content: Mutex<HashMap<String, watch::Sender<Option<ContentProgress>>>>,
+    updates: broadcast::Sender<ContentProgress>,
+}
+
+impl ContentMonitor {
+    /// Create the content monitor of the current user
+    pub async fn new(sdk: Arc<OriginSdk>) -> SdkResult<Self> {
+        let (user_id, _) = current_user_ids(&sdk).await?;
+        let (updates, _) = broadcast::channel(64);
+
+        Ok(Self {
+            sdk,
+            user_id,
+            content: Mutex::new(HashMap::new()),
+            updates,
+        })
+    }
+
+    /// Query the state of the content of the given games, or of all games if empty
+    pub async fn query(&self, game_ids: Vec<String>) -> SdkResult<Vec<ContentProgress>> {
+        let response = self
+            .sdk
+            .request(QueryContent {
+                user_id: self.user_id,
+                multiplayer_id: String::new(),
+                content_type: 0,
+                game_id: game_ids,
+            })
+            .await?;
+
+        Ok(self.update(&response.content).await)
+    }
+
+    /// Subscribe to all progress updates
+    pub fn updates(&self) -> broadcast::Receiver<ContentProgress> {
+        self.updates.subscribe()
+    }
+
+    /// Subscribe to the progress of a single piece of content
+    ///
+    /// The stream holds `None` until the first update for the content is received.
+    pub async fn subscribe(&self, content_id: &str) -> watch::Receiver<Option<ContentProgress>> {
+        self.content
+            .lock()
+            .await
+            .entry(content_id.to_string())
+            .or_insert_with(|| watch::channel(None).0)
+            .subscribe()
+    }
+
+    /// Last known progress of a piece of content
+    pub async fn progress(&self, content_id: &str) -> Option<ContentProgress> {
+        self.content
+            .lock()
+            .await
+            .get(content_id)
+            .and_then(|content| content.borrow().clone())
+    }
+
+    /// Last known progress of every piece of content
+    pub async fn all(&self) -> Vec<ContentProgress> {
+        self.content
+            .lock()
+            .await
+            .values()
+            .filter_map(|content| content.borrow().clone())
+            .collect()
+    }
+
+    /// Ask the client to start downloading an item
+    pub async fn start_download(&self, item_id: impl Into<String>) -> SdkResult<()> {
+        self.sdk
+            .request(StartDownload {
+                item_id: item_id.into(),
+            })
+            .await?;
+
+        Ok(())
+    }
+
+    /// Wait until a piece of content is ready to be used
+    ///
+    /// Fails if the content enters the [`ContentPhase::Error`] phase, or if the client
+    /// does not report the content when all content is queried.
+    pub async fn wait_ready(&self, content_id: &str) -> SdkResult<ContentProgress> {
+        if self.progress(content_id).await.is_none() {
+            self.query(Vec::new()).await?;
+        }
+
+        let mut content = self.subscribe(content_id).await;
+
+        loop {
+            let progress = content.borrow_and_update().clone();
+            if let Some(result) = ready_outcome(content_id, progress) {
+                return result;
+            }
+
+            content
+                .changed()
+                .await
+                .map_err(|_| SdkError::Other("Content monitor closed".to_string()))?;
+        }
+    }
+
+    /// Feed an event received from the server into the monitor
+    pub async fn handle_event(&self, event: &EventBody) {
+        if let EventBody::CoreContentUpdated(updated) = event {
+            self.update(&updated.games).await;
+        }
+    }
+
+    async fn update(&self, games: &[Game]) -> Vec<ContentProgress> {
+        let mut content = self.content.lock().await;
+
+        games
+            .iter()
+            .map(|game| {
+                let progress = ContentProgress::from(game);
+                debug!(
+                    "Content {} is {:?} ({:.0}%)",
+                    progress.content_id,
+                    progress.state,
+                    progress.progress * 100.0
+                );
+
+                content
+                    .entry(progress.content_id.clone())
+                    .or_insert_with(|| watch::channel(None).0)
+                    .send_replace(Some(progress.clone()));
+
+                let _ = self.updates.send(progress.clone());
+                progress
+            })
+            .collect()
+    }
+}
+
+/// Result of waiting for content given its last known progress, `None` to keep waiting
+fn ready_outcome(
+    content_id: &str,
+    progress: Option<ContentProgress>,
+) -> Option<SdkResult<ContentProgress>> {
+    let Some(progress) = progress else {
+        return Some(Err(SdkError::Other(format!(
+            "Content {} is unknown",
+            content_id
+        ))));
+    };
+
+    match progress.phase {
+        ContentPhase::Ready => Some(Ok(progress)),
+        ContentPhase::Error => Some(Err(SdkError::Other(format!(
+            "Content {} is in state {:?}",
+            content_id, progress.state
+        )))),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn progress(state: ContentState) -> ContentProgress {
+        ContentProgress {
+            content_id: "content".to_string(),
+            display_name: String::new(),
+            state,
+            phase: state.phase(),
+            progress: 0.0,
+            installed_version: String::new(),
+            available_version: String::new(),
+        }
+    }
+
+    #[test]
+    fn test_ready_outcome() {
+        assert!(matches!(ready_outcome("content", None), Some(Err(_))));
+        assert!(matches!(
+            ready_outcome("content", Some(progress(ContentState::InvalidContent))),
+            Some(Err(_))
+        ));
+        assert!(matches!(
+            ready_outcome("content", Some(progress(ContentState::Playing))),
+            Some(Ok(_))
+        ));
+        assert!(ready_outcome("content", Some(progress(ContentState::Updating))).is_none());
+    }
+}
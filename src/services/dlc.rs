@@ -0,0 +1,264 @@
+use std::{path::PathBuf, sync::Arc};
+
+use tokio::{
+    sync::{broadcast::error::RecvError, Mutex},
+    task::JoinHandle,
+};
+use tracing::{debug, warn};
+
+use crate::{
+    protocol::{
+        chunk::{ChunkState, ChunkStatus},
+        game::{Dlc, SetDlcInstalledState},
+    },
+    sdk::{OriginSdk, SdkResult},
+    services::{chunks::ChunkTracker, entitlements::EntitlementManager},
+};
+
+/// Where the content of a DLC is installed
+#[derive(Debug, Clone)]
+pub enum DlcContent {
+    /// A file or directory relative to the install directory
+    Path(PathBuf),
+    /// A progressive installation chunk
+    Chunk(i32),
+}
+
+/// A DLC of the game
+#[derive(Debug, Clone)]
+pub struct DlcDefinition {
+    /// Item id of the entitlement granting the DLC
+    pub id: String,
+    pub name: String,
+    pub content: DlcContent,
+}
+
+struct Syncer {
+    sdk: Arc<OriginSdk>,
+    entitlements: Arc<EntitlementManager>,
+    chunks: Option<Arc<ChunkTracker>>,
+    install_dir: PathBuf,
+    dlcs: Vec<DlcDefinition>,
+    reported: Mutex<Option<Vec<Dlc>>>,
+}
+
+impl Syncer {
+    async fn sync(&self, force: bool) -> SdkResult<Vec<Dlc>> {
+        let mut offers = Vec::with_capacity(self.dlcs.len());
+
+        for dlc in &self.dlcs {
+            let owned = self.entitlements.owns(&dlc.id).await?;
+
+            let (path_exists, chunk) = match &dlc.content {
+                DlcContent::Path(path) => {
+                    let exists = tokio::fs::try_exists(self.install_dir.join(path))
+                        .await
+                        .unwrap_or(false);
+                    (exists, None)
+                }
+                DlcContent::Chunk(chunk_id) => {
+                    let status = self
+                        .chunks
+                        .as_ref()
+                        .and_then(|chunks| chunks.status(*chunk_id));
+                    (false, status)
+                }
+            };
+
+            offers.push(Dlc {
+                name: dlc.name.clone(),
+                id: dlc.id.clone(),
+                installed: is_installed(&dlc.content, owned, path_exists, chunk.as_ref()),
+            });
+        }
+
+        let mut reported = self.reported.lock().await;
+        if !needs_report(reported.as_deref(), &offers, force) {
+            return Ok(offers);
+        }
+
+        self.sdk
+            .request(SetDlcInstalledState {
+                offers: offers.clone(),
+            })
+            .await?;
+
+        debug!(
+            "Reported {} of {} DLC as installed",
+            offers.iter().filter(|dlc| dlc.installed).count(),
+            offers.len()
+        );
+
+        *reported = Some(offers.clone());
+        Ok(offers)
+    }
+}
+
+/// A DLC is installed when it is owned and its content is present
+///
+/// `path_exists` applies to [`DlcContent::Path`], `chunk` to [`DlcContent::Chunk`].
+fn is_installed(
+    content: &DlcContent,
+    owned: bool,
+    path_exists: bool,
+    chunk: Option<&ChunkStatus>,
+) -> bool {
+    let present = match content {
+        DlcContent::Path(_) => path_exists,
+        DlcContent::Chunk(_) => chunk.is_some_and(|status| status.state == ChunkState::Installed),
+    };
+
+    owned && present
+}
+
+/// Returns true if the state must be sent, i.e. it differs from the last report
+fn needs_report(reported: Option<&[Dlc]>, offers: &[Dlc], force: bool) -> bool {
+    force || reported != Some(offers)
+}
+
+/// Keeps the DLC installed state reported to the client up to date
+///
+/// A DLC is installed when the user owns it and its content is present, either as a
+/// path inside `install_dir` or as an installed chunk. The state is reported with
+/// [`SetDlcInstalledState`] on start and again whenever the entitlements or chunk
+/// states change, if the result differs from the last report. The background task
+/// is stopped when the synchroniser is dropped.
+pub struct DlcSync {
+    syncer: Arc<Syncer>,
+    handle: JoinHandle<()>,
+}
+
+impl Drop for DlcSync {
+    fn drop(&mut self) {
+        self.handle.abort();
+    }
+}
+
+impl DlcSync {
+    /// Report the current state and start watching for changes
+    pub async fn start(
+        sdk: Arc<OriginSdk>,
+        entitlements: Arc<EntitlementManager>,
+        chunks: Option<Arc<ChunkTracker>>,
+        install_dir: impl Into<PathBuf>,
+        dlcs: Vec<DlcDefinition>,
+    ) -> SdkResult<Self> {
+        let syncer = Arc::new(Syncer {
+            sdk,
+            entitlements,
+            chunks,
+            install_dir: install_dir.into(),
+            dlcs,
+            reported: Mutex::new(None),
+        });
+
+        syncer.sync(true).await?;
+        let handle = tokio::spawn(Self::run(syncer.clone()));
+
+        Ok(Self { syncer, handle })
+    }
+
+    /// Check the DLC again and report the state if it changed
+    pub async fn sync(&self) -> SdkResult<Vec<Dlc>> {
+        self.syncer.sync(false).await
+    }
+
+    /// Last reported state
+    pub async fn reported(&self) -> Vec<Dlc> {
+        self.syncer
+            .reported
+            .lock()
+            .await
+            .clone()
+            .unwrap_or_default()
+    }
+
+    async fn run(syncer: Arc<Syncer>) {
+        let mut entitlements = syncer.entitlements.changes();
+        let mut chunks = syncer.chunks.as_ref().map(|chunks| chunks.subscribe());
+
+        loop {
+            let chunks_changed = async {
+                match chunks.as_mut() {
+                    Some(chunks) => chunks.changed().await.is_ok(),
+                    None => std::future::pending().await,
+                }
+            };
+
+            tokio::select! {
+                change = entitlements.recv() => match change {
+                    Ok(_) | Err(RecvError::Lagged(_)) => {}
+                    Err(RecvError::Closed) => break,
+                },
+                open = chunks_changed => if !open {
+                    chunks = None;
+                    continue;
+                },
+            }
+
+            if let Err(err) = syncer.sync(false).await {
+                warn!("Failed to report DLC installed state: {}", err);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::chunk::ChunkType;
+
+    fn chunk(state: ChunkState) -> ChunkStatus {
+        ChunkStatus {
+            chunk_id: 1,
+            name: String::new(),
+            item_id: "item".to_string(),
+            r#type: ChunkType::Normal,
+            state,
+            progress: 0.0,
+            size: 0,
+            chunk_eta: -1,
+            total_eta: -1,
+        }
+    }
+
+    fn dlc(installed: bool) -> Dlc {
+        Dlc {
+            name: "Expansion".to_string(),
+            id: "DLC1".to_string(),
+            installed,
+        }
+    }
+
+    #[test]
+    fn test_path_content_installed() {
+        let content = DlcContent::Path(PathBuf::from("dlc/expansion.pak"));
+
+        assert!(is_installed(&content, true, true, None));
+        assert!(!is_installed(&content, false, true, None));
+        assert!(!is_installed(&content, true, false, None));
+    }
+
+    #[test]
+    fn test_chunk_content_installed() {
+        let content = DlcContent::Chunk(1);
+        let installed = chunk(ChunkState::Installed);
+        let downloading = chunk(ChunkState::Downloading);
+
+        assert!(is_installed(&content, true, false, Some(&installed)));
+        assert!(!is_installed(&content, false, false, Some(&installed)));
+        assert!(!is_installed(&content, true, false, Some(&downloading)));
+        // Unknown chunk, e.g. without a chunk tracker
+        assert!(!is_installed(&content, true, true, None));
+    }
+
+    #[test]
+    fn test_needs_report() {
+        let offers = vec![dlc(true)];
+
+        assert!(needs_report(None, &offers, false));
+        assert!(!needs_report(Some(&[dlc(true)]), &offers, false));
+        assert!(needs_report(Some(&[dlc(false)]), &offers, false));
+        assert!(needs_report(Some(&[dlc(true)]), &offers, true));
+    }
+}
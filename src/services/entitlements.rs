@@ -0,0 +1,477 @@
+use std::{
+    collections::HashMap,
+    sync::Arc,
+    time::{Duration, SystemTime},
+};
+
+use tokio::sync::{broadcast, Mutex};
+use tracing::{debug, error};
+
+use crate::{
+    protocol::{
+        entitlements::{
+            ConsumeEntitlement, Entitlement, QueryEntitlements, QueryManifest, RefreshEntitlements,
+        },
+        EventBody,
+    },
+    sdk::{OriginSdk, SdkResult},
+    services::current_user_ids,
+};
+
+/// Cache key of an entitlement: item id and entitlement tag
+pub type EntitlementKey = (String, String);
+
+/// A single difference between two snapshots of the entitlements
+#[derive(Debug, Clone)]
+pub enum EntitlementChange {
+    /// A new entitlement was granted
+    Granted(Entitlement),
+    /// An entitlement is no longer returned by the server
+    Removed(Entitlement),
+    /// An entitlement changed, e.g. its use count after a consumption
+    Updated(Entitlement),
+}
+
+type EntitlementMap = HashMap<EntitlementKey, Entitlement>;
+
+/// Cached entitlements
+#[derive(Debug, Default)]
+enum Cache {
+    /// Never loaded, there is nothing to report changes against
+    #[default]
+    Unloaded,
+    Loaded(EntitlementMap),
+    /// Invalidated, kept to report the changes found by the next load
+    Stale(EntitlementMap),
+}
+
+impl Cache {
+    /// Changes between the cached entitlements and a full snapshot
+    fn changes_to(&self, new: &EntitlementMap) -> Vec<EntitlementChange> {
+        match self {
+            // Everything would be reported as newly granted on first load
+            Cache::Unloaded => Vec::new(),
+            Cache::Loaded(old) | Cache::Stale(old) => diff(old, new),
+        }
+    }
+}
+
+/// Entitlements of the current user cached by item id and entitlement tag
+///
+/// Loaded with [`QueryEntitlements`], refreshed on [`EventBody::PurchaseEvent`] and
+/// updated from [`EventBody::QueryEntitlementsResponse`] events. The cache is dropped
+/// on [`EventBody::Login`] events and loaded again for the user logged in then. Every
+/// difference with the previous state is published to [`EntitlementManager::changes`]
+/// subscribers.
+pub struct EntitlementManager {
+    sdk: Arc<OriginSdk>,
+    entitlements: Mutex<Cache>,
+    changes: broadcast::Sender<EntitlementChange>,
+}
+
+impl EntitlementManager {
+    /// Create the entitlement cache of the current user, loaded on first use
+    pub async fn new(sdk: Arc<OriginSdk>) -> SdkResult<Self> {
+        // Fail early if no user is logged in
+        current_user_ids(&sdk).await?;
+        let (changes, _) = broadcast::channel(64);
+
+        Ok(Self {
+            sdk,
+            entitlements: Mutex::new(Cache::Unloaded),
+            changes,
+        })
+    }
+
+    /// Subscribe to granted, removed and updated entitlements
+    pub fn changes(&self) -> broadcast::Receiver<EntitlementChange> {
+        self.changes.subscribe()
+    }
+
+    /// Ask the client to refresh its entitlements and reload the cache
+    pub async fn refresh(&self) -> SdkResult<Vec<EntitlementChange>> {
+        self.sdk.request(RefreshEntitlements).await?;
+        self.reload().await
+    }
+
+    /// Reload the cache from the entitlements currently known to the client
+    pub async fn reload(&self) -> SdkResult<Vec<EntitlementChange>> {
+        let response = self
+            .sdk
+            .request(QueryEntitlements {
+                user_id: self.user_id().await?,
+                offer_id: String::new(),
+                item_id: String::new(),
+                group: String::new(),
+                include_child_groups: true,
+                include_expired_trial_dlc: false,
+                filter_offers: Vec::new(),
+                filter_items: Vec::new(),
+                filter_groups: Vec::new(),
+            })
+            .await?;
+
+        Ok(self.replace(response.entitlements).await)
+    }
+
+    /// Query the current state of an entitlement from the server, bypassing the cache
+    pub async fn fetch(&self, item_id: &str, tag: &str) -> SdkResult<Option<Entitlement>> {
+        let response = self
+            .sdk
+            .request(QueryEntitlements {
+                user_id: self.user_id().await?,
+                offer_id: String::new(),
+                item_id: item_id.to_string(),
+                group: String::new(),
+                include_child_groups: true,
+                include_expired_trial_dlc: false,
+                filter_offers: Vec::new(),
+                filter_items: vec![item_id.to_string()],
+                filter_groups: Vec::new(),
+            })
+            .await?;
+
+        let entitlement = response
+            .entitlements
+            .iter()
+            .find(|entitlement| {
+                entitlement.item_id == item_id && entitlement.entitlement_tag == tag
+            })
+            .cloned();
+
+        self.upsert(response.entitlements).await;
+        Ok(entitlement)
+    }
+
+    /// Drop the cache, the next lookup queries the server again
+    ///
+    /// Changes found by that lookup are still reported against the dropped state.
+    pub async fn invalidate(&self) {
+        let mut cache = self.entitlements.lock().await;
+        if let Cache::Loaded(entitlements) = std::mem::take(&mut *cache) {
+            *cache = Cache::Stale(entitlements);
+        }
+    }
+
+    /// Every cached entitlement
+    pub async fn entitlements(&self) -> SdkResult<Vec<Entitlement>> {
+        self.with_cache(|cache| cache.values().cloned().collect())
+            .await
+    }
+
+    /// Look up an entitlement by item id and entitlement tag
+    pub async fn get(&self, item_id: &str, tag: &str) -> SdkResult<Option<Entitlement>> {
+        let key = (item_id.to_string(), tag.to_string());
+        self.with_cache(|cache| cache.get(&key).cloned()).await
+    }
+
+    /// Entitlements with the given tag
+    pub async fn by_tag(&self, tag: &str) -> SdkResult<Vec<Entitlement>> {
+        self.with_cache(|cache| {
+            cache
+                .values()
+                .filter(|entitlement| entitlement.entitlement_tag == tag)
+                .cloned()
+                .collect()
+        })
+        .await
+    }
+
+    /// Returns true if the user has an entitlement for the item that has not expired
+    pub async fn owns(&self, item_id: &str) -> SdkResult<bool> {
+        self.with_cache(|cache| {
+            cache
+                .values()
+                .any(|entitlement| entitlement.item_id == item_id && !entitlement.is_expired())
+        })
+        .await
+    }
+
+    /// Remaining uses of a consumable entitlement
+    pub async fn use_count(&self, item_id: &str, tag: &str) -> SdkResult<Option<i32>> {
+        Ok(self
+            .get(item_id, tag)
+            .await?
+            .map(|entitlement| entitlement.use_count))
+    }
+
+    /// Entitlements that have not expired yet but will within `within`
+    pub async fn expiring(&self, within: Duration) -> SdkResult<Vec<Entitlement>> {
+        let now = SystemTime::now();
+        let deadline = now + within;
+
+        self.with_cache(|cache| {
+            cache
+                .values()
+                .filter(|entitlement| {
+                    entitlement
+                        .expires_at()
+                        .is_some_and(|expiration| expiration > now && expiration <= deadline)
+                })
+                .cloned()
+                .collect()
+        })
+        .await
+    }
+
+    /// Resolve the entitlements granted by a purchase manifest
+    pub async fn manifest(&self, manifest: impl Into<String>) -> SdkResult<Vec<Entitlement>> {
+        let response = self
+            .sdk
+            .request(QueryManifest {
+                user_id: self.user_id().await?,
+                manifest: manifest.into(),
+            })
+            .await?;
+
+        self.upsert(response.entitlements.clone()).await;
+        Ok(response.entitlements)
+    }
+
+    /// Consume uses of an entitlement and cache its updated state
+    pub async fn consume(
+        &self,
+        entitlement: Entitlement,
+        uses: i32,
+        overuse: bool,
+    ) -> SdkResult<Entitlement> {
+        let response = self
+            .sdk
+            .request(ConsumeEntitlement {
+                user_id: self.user_id().await?,
+                uses,
+                b_overuse: overuse,
+                entitlement,
+            })
+            .await?;
+
+        self.upsert(vec![response.entitlement.clone()]).await;
+        Ok(response.entitlement)
+    }
+
+    /// Feed an event received from the server into the cache
+    pub async fn handle_event(&self, event: &EventBody) {
+        match event {
+            EventBody::PurchaseEvent(_) => {
+                if let Err(err) = self.refresh().await {
+                    error!("Failed to refresh entitlements after purchase: {}", err);
+                }
+            }
+            EventBody::QueryEntitlementsResponse(response) => {
+                self.upsert(response.entitlements.clone()).await;
+            }
+            EventBody::Login(_) => {
+                // The user may have changed, the next lookup loads the entitlements again
+                self.invalidate().await;
+            }
+            _ => {}
+        }
+    }
+
+    /// Id of the current user, resolved again after a [`EventBody::Login`]
+    async fn user_id(&self) -> SdkResult<u64> {
+        Ok(current_user_ids(&self.sdk).await?.0)
+    }
+
+    async fn with_cache<T>(&self, f: impl FnOnce(&EntitlementMap) -> T) -> SdkResult<T> {
+        {
+            let cache = self.entitlements.lock().await;
+            if let Cache::Loaded(cache) = &*cache {
+                return Ok(f(cache));
+            }
+        }
+
+        self.reload().await?;
+
+        let cache = self.entitlements.lock().await;
+        match &*cache {
+            Cache::Loaded(cache) => Ok(f(cache)),
+            _ => Ok(f(&HashMap::new())),
+        }
+    }
+
+    /// Replace the cache with a full snapshot, reporting removed entitlements too
+    async fn replace(&self, entitlements: Vec<Entitlement>) -> Vec<EntitlementChange> {
+        let new = index(entitlements);
+
+        let changes = {
+            let mut cache = self.entitlements.lock().await;
+            let changes = cache.changes_to(&new);
+
+            *cache = Cache::Loaded(new);
+            changes
+        };
+
+        self.publish(&changes);
+        changes
+    }
+
+    /// Merge a partial update into the cache
+    async fn upsert(&self, entitlements: Vec<Entitlement>) -> Vec<EntitlementChange> {
+        let changes = {
+            let mut cache = self.entitlements.lock().await;
+            let Cache::Loaded(cache) = &mut *cache else {
+                // Nothing to merge into, the next lookup loads the full list
+                return Vec::new();
+            };
+
+            let mut changes = Vec::new();
+            for (key, entitlement) in index(entitlements) {
+                match cache.insert(key, entitlement.clone()) {
+                    None => changes.push(EntitlementChange::Granted(entitlement)),
+                    Some(previous) if is_updated(&previous, &entitlement) => {
+                        changes.push(EntitlementChange::Updated(entitlement))
+                    }
+                    Some(_) => {}
+                }
+            }
+
+            changes
+        };
+
+        self.publish(&changes);
+        changes
+    }
+
+    fn publish(&self, changes: &[EntitlementChange]) {
+        if !changes.is_empty() {
+            debug!("Entitlements changed: {} differences", changes.len());
+        }
+
+        for change in changes {
+            let _ = self.changes.send(change.clone());
+        }
+    }
+}
+
+fn index(entitlements: Vec<Entitlement>) -> EntitlementMap {
+    entitlements
+        .into_iter()
+        .map(|entitlement| {
+            let key = (
+                entitlement.item_id.clone(),
+                entitlement.entitlement_tag.clone(),
+            );
+            (key, entitlement)
+        })
+        .collect()
+}
+
+fn is_updated(old: &Entitlement, new: &Entitlement) -> bool {
+    old.version != new.version
+        || old.use_count != new.use_count
+        || old.expiration != new.expiration
+        || old.entitlement_id != new.entitlement_id
+}
+
+/// Compare two snapshots of the entitlements
+fn diff(old: &EntitlementMap, new: &EntitlementMap) -> Vec<EntitlementChange> {
+    let mut changes = Vec::new();
+
+    for (key, entitlement) in new {
+        match old.get(key) {
+            None => changes.push(EntitlementChange::Granted(entitlement.clone())),
+            Some(previous) if is_updated(previous, entitlement) => {
+                changes.push(EntitlementChange::Updated(entitlement.clone()))
+            }
+            Some(_) => {}
+        }
+    }
+
+    for (key, entitlement) in old {
+        if !new.contains_key(key) {
+            changes.push(EntitlementChange::Removed(entitlement.clone()));
+        }
+    }
+
+    changes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entitlement(item_id: &str, use_count: i32, version: i32) -> Entitlement {
+        Entitlement {
+            type_: String::new(),
+            item_id: item_id.to_string(),
+            entitlement_id: format!("{}-1", item_id),
+            entitlement_tag: "tag".to_string(),
+            group: String::new(),
+            resource_id: String::new(),
+            use_count,
+            expiration: String::new(),
+            grant_date: String::new(),
+            last_modified_date: String::new(),
+            version,
+        }
+    }
+
+    fn granted(changes: &[EntitlementChange]) -> Vec<&str> {
+        let mut items: Vec<&str> = changes
+            .iter()
+            .filter_map(|change| match change {
+                EntitlementChange::Granted(entitlement) => Some(entitlement.item_id.as_str()),
+                _ => None,
+            })
+            .collect();
+        items.sort();
+        items
+    }
+
+    #[test]
+    fn test_is_updated() {
+        let old = entitlement("a", 5, 1);
+
+        assert!(!is_updated(&old, &entitlement("a", 5, 1)));
+        assert!(is_updated(&old, &entitlement("a", 4, 1)));
+        assert!(is_updated(&old, &entitlement("a", 5, 2)));
+
+        let mut expiring = entitlement("a", 5, 1);
+        expiring.expiration = "2030-01-01T00:00:00Z".to_string();
+        assert!(is_updated(&old, &expiring));
+
+        // Fields that do not affect ownership are ignored
+        let mut regrouped = entitlement("a", 5, 1);
+        regrouped.group = "other".to_string();
+        assert!(!is_updated(&old, &regrouped));
+    }
+
+    #[test]
+    fn test_diff() {
+        let old = index(vec![
+            entitlement("kept", 1, 1),
+            entitlement("consumed", 5, 1),
+            entitlement("removed", 1, 1),
+        ]);
+        let new = index(vec![
+            entitlement("kept", 1, 1),
+            entitlement("consumed", 4, 2),
+            entitlement("granted", 1, 1),
+        ]);
+
+        let changes = diff(&old, &new);
+        assert_eq!(changes.len(), 3);
+        assert_eq!(granted(&changes), ["granted"]);
+        assert!(changes.iter().any(|change| matches!(
+            change,
+            EntitlementChange::Updated(entitlement) if entitlement.item_id == "consumed"
+        )));
+        assert!(changes.iter().any(|change| matches!(
+            change,
+            EntitlementChange::Removed(entitlement) if entitlement.item_id == "removed"
+        )));
+    }
+
+    #[test]
+    fn test_changes_after_invalidation() {
+        let new = index(vec![entitlement("base", 1, 1), entitlement("dlc", 1, 1)]);
+
+        // The first load reports nothing
+        assert!(Cache::Unloaded.changes_to(&new).is_empty());
+
+        // A purchase loaded after an invalidation is still reported
+        let stale = Cache::Stale(index(vec![entitlement("base", 1, 1)]));
+        assert_eq!(granted(&stale.changes_to(&new)), ["dlc"]);
+    }
+}
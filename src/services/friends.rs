@@ -0,0 +1,306 @@
+use std::{collections::HashMap, sync::Arc};
+
+use tokio::sync::{broadcast, Mutex};
+use tracing::{debug, error};
+
+use crate::{
+    protocol::{
+        friends::{
+            Friend, FriendState, FriendStatus, QueryAreFriends, QueryFriends, RemoveFriend,
+            RequestFriend,
+        },
+        invites::AcceptFriendInvite,
+        EventBody,
+    },
+    sdk::{OriginSdk, SdkResult},
+    services::{
+        current_user_ids,
+        safety::{without_blocked_requests, Safety},
+    },
+};
+
+/// A single difference between two snapshots of the friends list
+#[derive(Debug, Clone)]
+pub enum FriendChange {
+    /// A user appeared in the friends list
+    Added(Friend),
+    /// A user disappeared from the friends list
+    Removed(Friend),
+    /// The relationship with a user changed, e.g. from [`FriendState::Request`] to
+    /// [`FriendState::Mutual`]
+    StateChanged {
+        user_id: u64,
+        from: FriendState,
+        to: FriendState,
+    },
+}
+
+/// Friends list of the current user
+///
+/// Loaded with [`QueryFriends`] and refreshed whenever a [`EventBody::FriendsEvent`],
+/// [`EventBody::PresenceEvent`] for a known friend, [`EventBody::BlockListUpdated`]
+/// or [`EventBody::Login`] is received, the latter loading the list of the user
+/// logged in then. Pending friend requests from users blocked in [`Safety`] are
+/// dropped before they reach the list. Every refresh is compared with the previous
+/// snapshot and the differences are published to [`FriendsList::changes`] subscribers.
+pub struct FriendsList {
+    sdk: Arc<OriginSdk>,
+    safety: Arc<Safety>,
+    friends: Mutex<HashMap<u64, Friend>>,
+    changes: broadcast::Sender<FriendChange>,
+}
+
+impl FriendsList {
+    /// Create the friends list of the current user and load it from the server
+    pub async fn new(sdk: Arc<OriginSdk>, safety: Arc<Safety>) -> SdkResult<Self> {
+        // Fail early if no user is logged in
+        current_user_ids(&sdk).await?;
+        let (changes, _) = broadcast::channel(64);
+
+        let list = Self {
+            sdk,
+            safety,
+            friends: Mutex::new(HashMap::new()),
+            changes,
+        };
+
+        list.refresh().await?;
+        Ok(list)
+    }
+
+    /// Subscribe to the differences found on every refresh
+    pub fn changes(&self) -> broadcast::Receiver<FriendChange> {
+        self.changes.subscribe()
+    }
+
+    /// Snapshot of every entry in the friends list
+    pub async fn friends(&self) -> Vec<Friend> {
+        self.friends.lock().await.values().cloned().collect()
+    }
+
+    /// Entries of the friends list in the given state
+    pub async fn with_state(&self, state: FriendState) -> Vec<Friend> {
+        self.friends
+            .lock()
+            .await
+            .values()
+            .filter(|friend| friend.state == state)
+            .cloned()
+            .collect()
+    }
+
+    /// Look up a single entry of the friends list
+    pub async fn get(&self, user_id: u64) -> Option<Friend> {
+        self.friends.lock().await.get(&user_id).cloned()
+    }
+
+    /// Reload the friends list from the server and return the differences
+    pub async fn refresh(&self) -> SdkResult<Vec<FriendChange>> {
+        let response = self
+            .sdk
+            .request(QueryFriends {
+                user_id: self.user_id().await?,
+            })
+            .await?;
+
+        Ok(self.apply(response.friends).await)
+    }
+
+    /// Send a friend request to a user
+    pub async fn request_friend(&self, user_id: u64) -> SdkResult<()> {
+        self.sdk
+            .request(RequestFriend {
+                user_id: self.user_id().await?,
+                user_to_add: user_id,
+            })
+            .await?;
+
+        self.refresh().await?;
+        Ok(())
+    }
+
+    /// Remove a user from the friends list
+    pub async fn remove_friend(&self, user_id: u64) -> SdkResult<()> {
+        self.sdk
+            .request(RemoveFriend {
+                user_id: self.user_id().await?,
+                user_to_remove: user_id,
+            })
+            .await?;
+
+        self.refresh().await?;
+        Ok(())
+    }
+
+    /// Accept a pending friend request from a user
+    pub async fn accept_invite(&self, user_id: u64) -> SdkResult<()> {
+        self.sdk
+            .request(AcceptFriendInvite {
+                user_id: self.user_id().await?,
+                other_id: user_id,
+            })
+            .await?;
+
+        self.refresh().await?;
+        Ok(())
+    }
+
+    /// Ask the server about the relationship with the given users
+    pub async fn are_friends(&self, users: Vec<u64>) -> SdkResult<Vec<FriendStatus>> {
+        let response = self
+            .sdk
+            .request(QueryAreFriends {
+                user_id: self.user_id().await?,
+                friends: users,
+            })
+            .await?;
+
+        Ok(response.users)
+    }
+
+    /// Feed an event received from the server into the friends list
+    pub async fn handle_event(&self, event: &EventBody) {
+        let refresh = match event {
+            EventBody::FriendsEvent(_) => true,
+            EventBody::BlockListUpdated(_) => {
+                // The filter must not depend on the order in which the game feeds the
+                // event to the safety module and to this list
+                if let Err(err) = self.safety.refresh_block_list().await {
+                    error!("Failed to refresh block list: {}", err);
+                }
+                true
+            }
+            EventBody::Login(login) => login.is_logged_in,
+            EventBody::PresenceEvent(event) => {
+                self.friends.lock().await.contains_key(&event.userid)
+            }
+            EventBody::QueryFriendsResponse(response) => {
+                self.apply(response.friends.clone()).await;
+                false
+            }
+            _ => false,
+        };
+
+        if refresh {
+            if let Err(err) = self.refresh().await {
+                error!("Failed to refresh friends list: {}", err);
+            }
+        }
+    }
+
+    /// Id of the current user, resolved again after a [`EventBody::Login`]
+    async fn user_id(&self) -> SdkResult<u64> {
+        Ok(current_user_ids(&self.sdk).await?.0)
+    }
+
+    async fn apply(&self, friends: Vec<Friend>) -> Vec<FriendChange> {
+        let blocked = self.safety.blocked_ids().await;
+        let new: HashMap<u64, Friend> = without_blocked_requests(friends, &blocked)
+            .into_iter()
+            .map(|friend| (friend.user_id, friend))
+            .collect();
+
+        let changes = {
+            let mut current = self.friends.lock().await;
+            let changes = diff_friends(&current, &new);
+            *current = new;
+            changes
+        };
+
+        if !changes.is_empty() {
+            debug!("Friends list changed: {} differences", changes.len());
+        }
+
+        for change in &changes {
+            // Sending only fails when nobody is subscribed
+            let _ = self.changes.send(change.clone());
+        }
+
+        changes
+    }
+}
+
+/// Compare two snapshots of the friends list
+fn diff_friends(old: &HashMap<u64, Friend>, new: &HashMap<u64, Friend>) -> Vec<FriendChange> {
+    let mut changes = Vec::new();
+
+    for (user_id, friend) in new {
+        match old.get(user_id) {
+            None => changes.push(FriendChange::Added(friend.clone())),
+            Some(previous) if previous.state != friend.state => {
+                changes.push(FriendChange::StateChanged {
+                    user_id: *user_id,
+                    from: previous.state,
+                    to: friend.state,
+                });
+            }
+            Some(_) => {}
+        }
+    }
+
+    for (user_id, friend) in old {
+        if !new.contains_key(user_id) {
+            changes.push(FriendChange::Removed(friend.clone()));
+        }
+    }
+
+    changes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::presence::Presence;
+
+    fn friend(user_id: u64, state: FriendState) -> (u64, Friend) {
+        let friend = Friend {
+            user_id,
+            persona_id: user_id,
+            persona: format!("user{}", user_id),
+            avatar_id: String::new(),
+            group: String::new(),
+            group_id: String::new(),
+            presence: Presence::Online,
+            state,
+            title_id: String::new(),
+            title: String::new(),
+            multiplayer_id: String::new(),
+            rich_presence: String::new(),
+            game_presence: String::new(),
+        };
+
+        (user_id, friend)
+    }
+
+    #[test]
+    fn test_diff_friends() {
+        let old = HashMap::from([
+            friend(1, FriendState::Mutual),
+            friend(2, FriendState::Request),
+            friend(3, FriendState::Invited),
+        ]);
+        let new = HashMap::from([
+            friend(1, FriendState::Mutual),
+            friend(2, FriendState::Mutual),
+            friend(4, FriendState::Invited),
+        ]);
+
+        let changes = diff_friends(&old, &new);
+        assert_eq!(changes.len(), 3);
+
+        assert!(changes.iter().any(|change| matches!(
+            change,
+            FriendChange::StateChanged {
+                user_id: 2,
+                from: FriendState::Request,
+                to: FriendState::Mutual
+            }
+        )));
+        assert!(changes
+            .iter()
+            .any(|change| matches!(change, FriendChange::Added(friend) if friend.user_id == 4)));
+        assert!(changes
+            .iter()
+            .any(|change| matches!(change, FriendChange::Removed(friend) if friend.user_id == 3)));
+    }
+}
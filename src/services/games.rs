@@ -0,0 +1,143 @@
+use std::{marker::PhantomData, sync::Arc};
+
+use serde::{de::DeserializeOwned, Serialize};
+use tokio::sync::broadcast;
+use tracing::{info, warn};
+
+use crate::{
+    protocol::{
+        game::{GetAllGameInfo, RestartGame, RestartOptions, SendGameMessage, StartGame},
+        EventBody,
+    },
+    sdk::{OriginSdk, SdkResult},
+    services::current_user_ids,
+};
+
+/// Launching other titles and restarting the current one
+pub struct GameLauncher {
+    sdk: Arc<OriginSdk>,
+    user_id: u64,
+}
+
+impl GameLauncher {
+    /// Create the launcher for the current user
+    pub async fn new(sdk: Arc<OriginSdk>) -> SdkResult<Self> {
+        let (user_id, _) = current_user_ids(&sdk).await?;
+        Ok(Self { sdk, user_id })
+    }
+
+    /// Launch another owned title by content id
+    pub async fn launch(
+        &self,
+        content_id: impl Into<String>,
+        command_line: impl Into<String>,
+    ) -> SdkResult<()> {
+        self.sdk
+            .request(StartGame {
+                game_id: content_id.into(),
+                multiplayer_id: String::new(),
+                command_line: command_line.into(),
+            })
+            .await?;
+
+        Ok(())
+    }
+
+    /// Ask the client to restart the current title
+    pub async fn restart(&self, options: RestartOptions) -> SdkResult<()> {
+        self.sdk
+            .request(RestartGame {
+                user_id: self.user_id,
+                options,
+            })
+            .await?;
+
+        Ok(())
+    }
+
+    /// Restart with a forced update if the installed version is outdated
+    ///
+    /// Returns true if a restart was requested, the game should then shut down.
+    pub async fn ensure_up_to_date(&self) -> SdkResult<bool> {
+        let info = self.sdk.request(GetAllGameInfo).await?;
+
+        if info.up_to_date {
+            return Ok(false);
+        }
+
+        info!(
+            "Installed version {} is outdated, {} is available",
+            info.installed_version, info.available_version
+        );
+
+        self.restart(RestartOptions::ForceUpdateForGame).await?;
+        Ok(true)
+    }
+}
+
+/// A message received from another title
+#[derive(Debug, Clone)]
+pub struct GameMessage<T> {
+    /// Title that sent the message
+    pub game_id: String,
+    pub payload: T,
+}
+
+/// Typed messaging between titles over [`SendGameMessage`] and [`EventBody::GameMessageEvent`]
+///
+/// Payloads are encoded as JSON. Received messages that do not decode into `T` are
+/// logged and dropped.
+pub struct GameChannel<T> {
+    sdk: Arc<OriginSdk>,
+    messages: broadcast::Sender<GameMessage<T>>,
+    _payload: PhantomData<fn() -> T>,
+}
+
+impl<T> GameChannel<T>
+where
+    T: Serialize + DeserializeOwned + Clone + Send + 'static,
+{
+    pub fn new(sdk: Arc<OriginSdk>) -> Self {
+        let (messages, _) = broadcast::channel(64);
+
+        Self {
+            sdk,
+            messages,
+            _payload: PhantomData,
+        }
+    }
+
+    /// Subscribe to messages received from other titles
+    pub fn subscribe(&self) -> broadcast::Receiver<GameMessage<T>> {
+        self.messages.subscribe()
+    }
+
+    /// Send a message to another title
+    pub async fn send(&self, game_id: impl Into<String>, payload: &T) -> SdkResult<()> {
+        self.sdk
+            .request(SendGameMessage {
+                game_id: game_id.into(),
+                message: serde_json::to_string(payload)?,
+            })
+            .await?;
+
+        Ok(())
+    }
+
+    /// Feed an event received from the server into the channel
+    pub async fn handle_event(&self, event: &EventBody) {
+        let EventBody::GameMessageEvent(event) = event else {
+            return;
+        };
+
+        match serde_json::from_str(&event.message) {
+            Ok(payload) => {
+                let _ = self.messages.send(GameMessage {
+                    game_id: event.game_id.clone(),
+                    payload,
+                });
+            }
+            Err(err) => warn!("Dropping message from {}: {}", event.game_id, err),
+        }
+    }
+}
@@ -0,0 +1,406 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+use tracing::{debug, warn};
+
+use crate::{
+    protocol::system::{Image, QueryImage},
+    sdk::{OriginSdk, SdkError, SdkResult},
+};
+
+/// Cache key of an image: image id, width and height
+pub type ImageKey = (String, i32, i32);
+
+/// A resolved image kept in the cache index
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct CachedImage {
+    /// Requested image id and size, the server may return another size
+    #[serde(rename = "@Id", default)]
+    id: String,
+    #[serde(rename = "@Width", default)]
+    width: i32,
+    #[serde(rename = "@Height", default)]
+    height: i32,
+    #[serde(rename = "Image")]
+    image: Image,
+    /// Seconds since the Unix epoch when the image was last requested
+    #[serde(rename = "@LastUsed")]
+    last_used: u64,
+    /// Name of the local copy of the file inside the cache directory, if any
+    #[serde(rename = "@File", default)]
+    file: String,
+}
+
+/// On-disk representation of the cache index
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(rename = "ImageCache")]
+struct ImageIndex {
+    #[serde(rename = "Entry", default)]
+    entries: Vec<CachedImage>,
+}
+
+/// Image resolver with a persistent cache
+///
+/// Resolves avatar ids of friends and profiles and image ids of achievements, offers
+/// and categories with [`QueryImage`]. Results are kept in an index inside `dir`,
+/// together with copies of the image files when requested with [`ImageCache::load`].
+/// Once more than `capacity` images are cached the least recently used ones are
+/// evicted. Concurrent requests for the same image share a single query.
+pub struct ImageCache {
+    sdk: Arc<OriginSdk>,
+    dir: PathBuf,
+    capacity: usize,
+    entries: Mutex<HashMap<ImageKey, CachedImage>>,
+    in_flight: Mutex<HashMap<ImageKey, Arc<Mutex<()>>>>,
+}
+
+impl ImageCache {
+    /// Create the cache in `dir`, restoring the index left by a previous run
+    pub async fn new(
+        sdk: Arc<OriginSdk>,
+        dir: impl Into<PathBuf>,
+        capacity: usize,
+    ) -> SdkResult<Self> {
+        let dir = dir.into();
+        tokio::fs::create_dir_all(&dir)
+            .await
+            .map_err(SdkError::Storage)?;
+
+        let index = Self::load_index(&dir.join("index.xml")).await?;
+        let entries = index
+            .entries
+            .into_iter()
+            .map(|entry| (entry.key(), entry))
+            .collect();
+
+        Ok(Self {
+            sdk,
+            dir,
+            capacity,
+            entries: Mutex::new(entries),
+            in_flight: Mutex::new(HashMap::new()),
+        })
+    }
+
+    /// Resolve an image at the requested size
+    pub async fn resolve(&self, image_id: &str, width: i32, height: i32) -> SdkResult<Image> {
+        let key = (image_id.to_string(), width, height);
+
+        if let Some(image) = self.touch(&key).await {
+            return Ok(image.image);
+        }
+
+        // Only one request per image, the others wait and read the cached result
+        let lock = self
+            .in_flight
+            .lock()
+            .await
+            .entry(key.clone())
+            .or_default()
+            .clone();
+        let _guard = lock.lock().await;
+
+        let result = match self.touch(&key).await {
+            Some(image) => Ok(image.image),
+            None => self.query(&key).await,
+        };
+
+        self.in_flight.lock().await.remove(&key);
+        result
+    }
+
+    /// Resolve an image and read its contents, keeping a copy in the cache directory
+    pub async fn load(&self, image_id: &str, width: i32, height: i32) -> SdkResult<Vec<u8>> {
+        let image = self.resolve(image_id, width, height).await?;
+        let key = (image_id.to_string(), width, height);
+
+        let file = self
+            .entries
+            .lock()
+            .await
+            .get(&key)
+            .map(|entry| entry.file.clone())
+            .unwrap_or_default();
+
+        if !file.is_empty() {
+            match tokio::fs::read(self.dir.join(&file)).await {
+                Ok(bytes) => return Ok(bytes),
+                Err(err) => warn!("Cached copy of {} is unreadable: {}", image.image_id, err),
+            }
+        }
+
+        let bytes = tokio::fs::read(&image.resource_path)
+            .await
+            .map_err(SdkError::Storage)?;
+
+        let file = file_name(&key, &image.resource_path);
+        tokio::fs::write(self.dir.join(&file), &bytes)
+            .await
+            .map_err(SdkError::Storage)?;
+
+        if let Some(entry) = self.entries.lock().await.get_mut(&key) {
+            entry.file = file;
+        }
+
+        self.store_index().await?;
+        Ok(bytes)
+    }
+
+    /// Drop every cached image and the local copies of their files
+    pub async fn clear(&self) -> SdkResult<()> {
+        let entries: Vec<CachedImage> = self
+            .entries
+            .lock()
+            .await
+            .drain()
+            .map(|(_, entry)| entry)
+            .collect();
+
+        for entry in entries {
+            self.remove_file(&entry).await;
+        }
+
+        self.store_index().await
+    }
+
+    async fn query(&self, key: &ImageKey) -> SdkResult<Image> {
+        let response = self
+            .sdk
+            .request(QueryImage {
+                image_id: key.0.clone(),
+                width: key.1,
+                height: key.2,
+            })
+            .await?;
+
+        let image = response.images.into_iter().next().ok_or_else(|| {
+            SdkError::Other(format!(
+                "No image {} at {}x{} (result {})",
+                key.0, key.1, key.2, response.result
+            ))
+        })?;
+
+        debug!("Resolved image {} to {}", key.0, image.resource_path);
+
+        let evicted = {
+            let mut entries = self.entries.lock().await;
+            entries.insert(
+                key.clone(),
+                CachedImage {
+                    id: key.0.clone(),
+                    width: key.1,
+                    height: key.2,
+                    image: image.clone(),
+                    last_used: now(),
+                    file: String::new(),
+                },
+            );
+
+            evict(&mut entries, self.capacity)
+        };
+
+        for entry in &evicted {
+            self.remove_file(entry).await;
+        }
+
+        self.store_index().await?;
+        Ok(image)
+    }
+
+    /// Look up a cached image and mark it as recently used
+    async fn touch(&self, key: &ImageKey) -> Option<CachedImage> {
+        let mut entries = self.entries.lock().await;
+        let entry = entries.get_mut(key)?;
+        entry.last_used = now();
+        Some(entry.clone())
+    }
+
+    async fn remove_file(&self, entry: &CachedImage) {
+        if entry.file.is_empty() {
+            return;
+        }
+
+        if let Err(err) = tokio::fs::remove_file(self.dir.join(&entry.file)).await {
+            if err.kind() != std::io::ErrorKind::NotFound {
+                warn!("Failed to remove cached image {}: {}", entry.file, err);
+            }
+        }
+    }
+
+    async fn load_index(path: &Path) -> SdkResult<ImageIndex> {
+        let xml = match tokio::fs::read_to_string(path).await {
+            Ok(xml) => xml,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+                return Ok(ImageIndex::default())
+            }
+            Err(err) => return Err(SdkError::Storage(err)),
+        };
+
+        Ok(quick_xml::de::from_str(&xml)?)
+    }
+
+    async fn store_index(&self) -> SdkResult<()> {
+        let index = ImageIndex {
+            entries: self.entries.lock().await.values().cloned().collect(),
+        };
+
+        let xml = quick_xml::se::to_string(&index)?;
+        tokio::fs::write(self.dir.join("index.xml"), xml)
+            .await
+            .map_err(SdkError::Storage)
+    }
+}
+
+impl CachedImage {
+    /// Key the image was requested with
+    fn key(&self) -> ImageKey {
+        // Indexes written before the requested key was stored
+        if self.id.is_empty() {
+            return (
+                self.image.image_id.clone(),
+                self.image.width,
+                self.image.height,
+            );
+        }
+
+        (self.id.clone(), self.width, self.height)
+    }
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs())
+        .unwrap_or_default()
+}
+
+/// Remove the least recently used entries above `capacity` and return them
+fn evict(entries: &mut HashMap<ImageKey, CachedImage>, capacity: usize) -> Vec<CachedImage> {
+    if entries.len() <= capacity {
+        return Vec::new();
+    }
+
+    let mut by_age: Vec<(ImageKey, u64)> = entries
+        .iter()
+        .map(|(key, entry)| (key.clone(), entry.last_used))
+        .collect();
+    by_age.sort_by_key(|(_, last_used)| *last_used);
+
+    let excess = entries.len() - capacity;
+    by_age
+        .into_iter()
+        .take(excess)
+        .filter_map(|(key, _)| entries.remove(&key))
+        .collect()
+}
+
+/// Name of the local copy of an image, keeping the extension of the original file
+///
+/// Characters of the id other than ASCII letters, digits and `-` are percent-encoded,
+/// so distinct ids never share a file.
+fn file_name(key: &ImageKey, resource_path: &str) -> String {
+    let mut id = String::with_capacity(key.0.len());
+    for byte in key.0.bytes() {
+        if byte.is_ascii_alphanumeric() || byte == b'-' {
+            id.push(byte as char);
+        } else {
+            id.push_str(&format!("%{:02X}", byte));
+        }
+    }
+
+    let extension = Path::new(resource_path)
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .unwrap_or("img");
+
+    format!("{}_{}x{}.{}", id, key.1, key.2, extension)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(image_id: &str, last_used: u64) -> (ImageKey, CachedImage) {
+        let image = Image {
+            image_id: image_id.to_string(),
+            width: 64,
+            height: 64,
+            resource_path: format!("C:\\Cache\\{}.png", image_id),
+        };
+
+        let entry = CachedImage {
+            id: image_id.to_string(),
+            width: 64,
+            height: 64,
+            image,
+            last_used,
+            file: String::new(),
+        };
+
+        (entry.key(), entry)
+    }
+
+    #[test]
+    fn test_evict_least_recently_used() {
+        let mut entries = HashMap::from([entry("a", 30), entry("b", 10), entry("c", 20)]);
+
+        let evicted = evict(&mut entries, 2);
+        assert_eq!(evicted.len(), 1);
+        assert_eq!(evicted[0].image.image_id, "b");
+        assert_eq!(entries.len(), 2);
+
+        assert!(evict(&mut entries, 2).is_empty());
+    }
+
+    #[test]
+    fn test_index_roundtrip() {
+        let index = ImageIndex {
+            entries: vec![entry("avatar:1", 5).1],
+        };
+
+        let xml = quick_xml::se::to_string(&index).expect("Failed to serialize");
+        let restored: ImageIndex = quick_xml::de::from_str(&xml).expect("Failed to deserialize");
+
+        assert_eq!(restored.entries.len(), 1);
+        assert_eq!(restored.entries[0].image.image_id, "avatar:1");
+        assert_eq!(restored.entries[0].last_used, 5);
+        assert_eq!(
+            file_name(&restored.entries[0].key(), "C:\\Cache\\a.png"),
+            "avatar%3A1_64x64.png"
+        );
+    }
+
+    #[test]
+    fn test_restore_requested_key() {
+        // The server returned another size than the one requested
+        let (_, mut cached) = entry("avatar:1", 5);
+        cached.width = 40;
+        cached.height = 40;
+
+        let index = ImageIndex {
+            entries: vec![cached],
+        };
+
+        let xml = quick_xml::se::to_string(&index).expect("Failed to serialize");
+        let restored: ImageIndex = quick_xml::de::from_str(&xml).expect("Failed to deserialize");
+
+        assert_eq!(restored.entries[0].key(), ("avatar:1".to_string(), 40, 40));
+        assert_eq!(restored.entries[0].image.width, 64);
+    }
+
+    #[test]
+    fn test_file_name_is_unique() {
+        let name = |id: &str| file_name(&(id.to_string(), 64, 64), "a.png");
+
+        assert_ne!(name("avatar:1"), name("avatar_1"));
+        assert_ne!(name("a_1"), name("a%5F1"));
+        assert_eq!(name("avatar-1"), "avatar-1_64x64.png");
+        assert_eq!(name("../x"), "%2E%2E%2Fx_64x64.png");
+    }
+}
@@ -0,0 +1,181 @@
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use tokio::sync::Mutex;
+use tracing::{debug, warn};
+
+use crate::{
+    protocol::{
+        entitlements::{InvalidateLicense, RequestLicense},
+        EventBody,
+    },
+    sdk::{OriginSdk, SdkError, SdkResult},
+    services::current_user_ids,
+};
+
+/// Default time a license is reused before a new one is requested
+pub const DEFAULT_LICENSE_TTL: Duration = Duration::from_secs(60 * 60);
+
+/// A license returned by [`RequestLicense`]
+///
+/// The format of the license blob is not documented, so it is kept as returned and
+/// its own expiry cannot be read. The cached copy is requested again after the ttl
+/// given to [`Licensing::new`] instead.
+#[derive(Debug, Clone)]
+pub struct License {
+    /// License blob as returned by the client
+    pub value: String,
+    pub obtained_at: Instant,
+    /// Time after which the license is requested again
+    pub cached_until: Instant,
+}
+
+impl License {
+    /// Wrap a license blob, rejecting empty ones
+    pub fn new(value: String, ttl: Duration) -> SdkResult<Self> {
+        if value.trim().is_empty() {
+            return Err(SdkError::Other("Received an empty license".to_string()));
+        }
+
+        let obtained_at = Instant::now();
+
+        Ok(Self {
+            value,
+            obtained_at,
+            cached_until: obtained_at + ttl,
+        })
+    }
+
+    /// Returns true if the license should be requested again
+    pub fn is_stale(&self) -> bool {
+        Instant::now() >= self.cached_until
+    }
+}
+
+/// License handling for DRM-aware games
+///
+/// Requests a license with the configured ticket and ticket engine, caches it for
+/// `ttl` and drops it when the user logs out or their entitlements change, so the
+/// next [`Licensing::ensure_licensed`] call requests a new one.
+pub struct Licensing {
+    sdk: Arc<OriginSdk>,
+    user_id: u64,
+    request_ticket: String,
+    ticket_engine: i32,
+    ttl: Duration,
+    license: Mutex<Option<License>>,
+}
+
+impl Licensing {
+    /// Create the license handler for the current user
+    pub async fn new(
+        sdk: Arc<OriginSdk>,
+        request_ticket: impl Into<String>,
+        ticket_engine: i32,
+        ttl: Duration,
+    ) -> SdkResult<Self> {
+        let (user_id, _) = current_user_ids(&sdk).await?;
+
+        Ok(Self {
+            sdk,
+            user_id,
+            request_ticket: request_ticket.into(),
+            ticket_engine,
+            ttl,
+            license: Mutex::new(None),
+        })
+    }
+
+    /// Return the cached license, requesting a new one if there is none or it expired
+    pub async fn ensure_licensed(&self) -> SdkResult<License> {
+        let mut cached = self.license.lock().await;
+
+        if let Some(license) = cached.as_ref() {
+            if !license.is_stale() {
+                return Ok(license.clone());
+            }
+        }
+
+        let response = self
+            .sdk
+            .request(RequestLicense {
+                user_id: self.user_id,
+                request_ticket: self.request_ticket.clone(),
+                ticket_engine: self.ticket_engine,
+            })
+            .await?;
+
+        let license = License::new(response.license, self.ttl)?;
+        debug!("Obtained license, caching it for {:?}", self.ttl);
+
+        *cached = Some(license.clone());
+        Ok(license)
+    }
+
+    /// Cached license, without requesting one
+    pub async fn license(&self) -> Option<License> {
+        self.license
+            .lock()
+            .await
+            .clone()
+            .filter(|license| !license.is_stale())
+    }
+
+    /// Invalidate the license on the client and drop the cached one
+    pub async fn invalidate(&self) -> SdkResult<()> {
+        self.license.lock().await.take();
+
+        self.sdk
+            .request(InvalidateLicense {
+                user_id: self.user_id,
+            })
+            .await?;
+
+        Ok(())
+    }
+
+    /// Feed an event received from the server into the license handler
+    pub async fn handle_event(&self, event: &EventBody) {
+        match event {
+            EventBody::Login(login) if !login.is_logged_in => {
+                if let Err(err) = self.invalidate().await {
+                    warn!("Failed to invalidate license after logout: {}", err);
+                }
+            }
+            EventBody::PurchaseEvent(_) | EventBody::QueryEntitlementsResponse(_) => {
+                debug!("Entitlements changed, dropping cached license");
+                self.license.lock().await.take();
+            }
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_license_blob_kept_verbatim() {
+        // Wrapped base64 as some license servers return it
+        let blob = "TUlJQ0lqQU5CZ2txaGtpRzl3MEJBUUVGQUFPQ0FnOEFNSUlDQ2dLQ0FnRUF\r\nRkZGRg==\n";
+        let license = License::new(blob.to_string(), DEFAULT_LICENSE_TTL).unwrap();
+
+        assert_eq!(license.value, blob);
+        assert!(!license.is_stale());
+    }
+
+    #[test]
+    fn test_empty_license_rejected() {
+        assert!(License::new(String::new(), DEFAULT_LICENSE_TTL).is_err());
+        assert!(License::new(" \r\n".to_string(), DEFAULT_LICENSE_TTL).is_err());
+    }
+
+    #[test]
+    fn test_license_stale_after_ttl() {
+        let license = License::new("blob".to_string(), Duration::ZERO).unwrap();
+        assert!(license.is_stale());
+    }
+}
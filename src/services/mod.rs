@@ -0,0 +1,45 @@
+//! High-level services built on top of [`OriginSdk`].
+//!
+//! The modules in [`crate::protocol`] map one-to-one to LSX messages. The services
+//! here combine those messages into stateful components that games usually end up
+//! writing by hand. Services never read the event channel themselves: forward every
+//! received [`Event`](crate::protocol::Event) to their `handle_event` method.
+//!
+//! Some handlers send requests and wait for their responses, e.g. to refresh a cache.
+//! Responses are delivered independently of the event channel, so awaiting handlers
+//! one after the other in the event loop is fine. Events received meanwhile are
+//! queued by [`OriginSdk`] until the loop reads them, up to a fixed limit after which
+//! new events are dropped.
+
+use crate::sdk::{OriginSdk, SdkResult};
+
+pub mod achievements;
+pub mod auth;
+pub mod broadcast;
+pub mod chat;
+pub mod chunks;
+pub mod consumables;
+pub mod content;
+pub mod dlc;
+pub mod entitlements;
+pub mod friends;
+pub mod games;
+pub mod images;
+pub mod license;
+pub mod overlay;
+pub mod party;
+pub mod permissions;
+pub mod presence;
+pub mod recent_players;
+pub mod safety;
+pub mod session;
+pub mod settings;
+pub mod steam;
+pub mod store;
+pub mod trial;
+
+/// User and persona ids of the currently logged in user
+pub(crate) async fn current_user_ids(sdk: &OriginSdk) -> SdkResult<(u64, u64)> {
+    let profile = sdk.current_user().await?;
+    Ok((profile.user_id, profile.persona_id))
+}
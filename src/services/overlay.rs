@@ -0,0 +1,144 @@
+use std::sync::Arc;
+
+use tokio::sync::watch;
+use tracing::{debug, warn};
+
+use crate::{
+    protocol::{
+        overlay::{IgoState, OverlayStateChanged, ShowIgo, ShowIgoWindow},
+        EventBody,
+    },
+    sdk::{OriginSdk, SdkResult},
+    services::current_user_ids,
+};
+
+/// State of the in-game overlay and of the game window
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct OverlayState {
+    /// The overlay is drawn on top of the game
+    pub visible: bool,
+    /// The client asked the game to minimize its window
+    pub minimized: bool,
+    /// Reason code of the last [`EventBody::IgoUnavailable`], cleared when the overlay shows up
+    ///
+    /// Only informative, requests to open the overlay are still sent and the client
+    /// decides whether they fail.
+    pub unavailable: Option<i32>,
+}
+
+impl OverlayState {
+    /// Returns true if the game should stop processing input
+    pub fn input_paused(&self) -> bool {
+        self.visible || self.minimized
+    }
+}
+
+/// In-game overlay controller
+///
+/// Tracks the overlay state from [`EventBody::IgoEvent`], [`EventBody::IgoUnavailable`],
+/// [`EventBody::MinimizeRequest`] and [`EventBody::RestoreRequest`] events, acknowledges
+/// overlay state changes with [`OverlayStateChanged`] and opens overlay windows built
+/// with the [`ShowIgoWindow`] constructors.
+pub struct OverlayController {
+    sdk: Arc<OriginSdk>,
+    user_id: u64,
+    state: watch::Sender<OverlayState>,
+}
+
+impl OverlayController {
+    /// Create the overlay controller of the current user
+    pub async fn new(sdk: Arc<OriginSdk>) -> SdkResult<Self> {
+        let (user_id, _) = current_user_ids(&sdk).await?;
+        let (state, _) = watch::channel(OverlayState::default());
+
+        Ok(Self {
+            sdk,
+            user_id,
+            state,
+        })
+    }
+
+    /// Current overlay state
+    pub fn state(&self) -> OverlayState {
+        self.state.borrow().clone()
+    }
+
+    /// Subscribe to overlay state changes, e.g. to pause game input
+    pub fn subscribe(&self) -> watch::Receiver<OverlayState> {
+        self.state.subscribe()
+    }
+
+    /// Returns true if the game should stop processing input
+    pub fn input_paused(&self) -> bool {
+        self.state.borrow().input_paused()
+    }
+
+    /// Show or hide the overlay
+    pub async fn show(&self, show: bool) -> SdkResult<()> {
+        self.sdk.request(ShowIgo { b_show: show }).await?;
+        Ok(())
+    }
+
+    /// Open an overlay window built with one of the [`ShowIgoWindow`] constructors
+    pub async fn show_window(&self, window: ShowIgoWindow) -> SdkResult<()> {
+        self.sdk.request(window).await?;
+        Ok(())
+    }
+
+    /// Open the profile of another user
+    pub async fn open_profile(&self, target_id: u64) -> SdkResult<()> {
+        self.show_window(ShowIgoWindow::profile(self.user_id, target_id))
+            .await
+    }
+
+    /// Open the store filtered to the given categories and offers
+    pub async fn open_store(&self, categories: Vec<String>, offers: Vec<String>) -> SdkResult<()> {
+        self.show_window(ShowIgoWindow::store(self.user_id, categories, offers))
+            .await
+    }
+
+    /// Open the checkout of the given offers
+    pub async fn open_checkout(&self, offers: Vec<String>) -> SdkResult<()> {
+        self.show_window(ShowIgoWindow::checkout(self.user_id, offers))
+            .await
+    }
+
+    /// Feed an event received from the server into the controller
+    pub async fn handle_event(&self, event: &EventBody) {
+        match event {
+            EventBody::IgoEvent(igo) => {
+                let visible = igo.state == IgoState::Up;
+                debug!("Overlay is {:?}", igo.state);
+
+                self.state.send_modify(|state| {
+                    state.visible = visible;
+                    state.unavailable = None;
+                });
+
+                let result = self
+                    .sdk
+                    .request(OverlayStateChanged { state: igo.state })
+                    .await;
+
+                if let Err(err) = result {
+                    warn!("Failed to acknowledge overlay state change: {}", err);
+                }
+            }
+            EventBody::IgoUnavailable(unavailable) => {
+                warn!("Overlay is unavailable (reason {})", unavailable.reason);
+
+                self.state.send_modify(|state| {
+                    state.visible = false;
+                    state.unavailable = Some(unavailable.reason);
+                });
+            }
+            EventBody::MinimizeRequest(_) => {
+                self.state.send_modify(|state| state.minimized = true);
+            }
+            EventBody::RestoreRequest(_) => {
+                self.state.send_modify(|state| state.minimized = false);
+            }
+            _ => {}
+        }
+    }
+}
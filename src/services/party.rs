@@ -0,0 +1,540 @@
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+};
+
+use tokio::sync::Mutex;
+use tracing::{debug, error};
+
+use crate::{
+    protocol::{
+        friends::Friend,
+        groups::{
+            CreateGroup, EnterGroup, GroupInfo, GroupType, LeaveGroup, QueryGroup,
+            RemoveUsersFromGroup,
+        },
+        invites::{AcceptInvite, InviteUsersToGroup, SendGroupGameInvite},
+        voip::{EnableVoip, EnumMuteState},
+        EventBody,
+    },
+    sdk::{OriginSdk, SdkError, SdkResult},
+    services::{current_user_ids, safety::Safety},
+};
+
+/// A member of the party
+#[derive(Debug, Clone)]
+pub struct PartyMember {
+    pub friend: Friend,
+    /// Mute state tracked by [`Safety`], [`EnumMuteState::None`] if unknown
+    pub mute_state: EnumMuteState,
+}
+
+/// Kind of invite received by the current user
+#[derive(Debug, Clone)]
+pub enum InviteKind {
+    /// Invite to join a group
+    Group { group_type: GroupType },
+    /// Invite to join a multiplayer session
+    Game {
+        multiplayer_id: String,
+        session_information: String,
+    },
+}
+
+/// An invite waiting to be accepted
+#[derive(Debug, Clone)]
+pub struct PartyInvite {
+    pub from: u64,
+    pub group_id: String,
+    pub group_name: String,
+    pub kind: InviteKind,
+}
+
+#[derive(Debug)]
+struct PartyState {
+    info: GroupInfo,
+    members: HashMap<u64, Friend>,
+}
+
+/// Party of the current user built on top of groups, invites and VoIP
+///
+/// Tracks the group the user is in along with its members and the permissions reported
+/// in [`GroupInfo`]. Muting goes through [`Safety`], which owns the mute state of every
+/// group and must receive the events as well. Invites received through
+/// [`EventBody::GroupInviteEvent`] and [`EventBody::MultiplayerInvite`] are kept until
+/// they are accepted or declined, unless the sender is blocked in [`Safety`].
+pub struct Party {
+    sdk: Arc<OriginSdk>,
+    safety: Arc<Safety>,
+    user_id: u64,
+    state: Mutex<Option<PartyState>>,
+    invites: Mutex<Vec<PartyInvite>>,
+}
+
+impl Party {
+    /// Create a party handle for the current user, not joined to any group
+    pub async fn new(sdk: Arc<OriginSdk>, safety: Arc<Safety>) -> SdkResult<Self> {
+        let (user_id, _) = current_user_ids(&sdk).await?;
+
+        Ok(Self {
+            sdk,
+            safety,
+            user_id,
+            state: Mutex::new(None),
+            invites: Mutex::new(Vec::new()),
+        })
+    }
+
+    /// Information about the group the user is in
+    pub async fn info(&self) -> Option<GroupInfo> {
+        self.state
+            .lock()
+            .await
+            .as_ref()
+            .map(|state| state.info.clone())
+    }
+
+    /// Members of the party, including the current user
+    pub async fn members(&self) -> Vec<PartyMember> {
+        let (group_id, friends) = {
+            let state = self.state.lock().await;
+            let Some(state) = state.as_ref() else {
+                return Vec::new();
+            };
+
+            let friends: Vec<Friend> = state.members.values().cloned().collect();
+            (state.info.group_id.clone(), friends)
+        };
+
+        let mutes = self.safety.group_mute_state(&group_id).await;
+
+        friends
+            .into_iter()
+            .map(|friend| PartyMember {
+                mute_state: mutes
+                    .get(&friend.user_id)
+                    .copied()
+                    .unwrap_or(EnumMuteState::None),
+                friend,
+            })
+            .collect()
+    }
+
+    /// Mute state of a single member
+    pub async fn mute_state(&self, user_id: u64) -> Option<EnumMuteState> {
+        let group_id = {
+            let state = self.state.lock().await;
+            let state = state.as_ref()?;
+            state.members.get(&user_id)?;
+            state.info.group_id.clone()
+        };
+
+        Some(self.safety.mute_state(&group_id, user_id).await)
+    }
+
+    /// Invites that have not been accepted or declined yet
+    pub async fn pending_invites(&self) -> Vec<PartyInvite> {
+        self.invites.lock().await.clone()
+    }
+
+    /// Create a new group and enter it
+    pub async fn create(&self, name: impl Into<String>, group_type: GroupType) -> SdkResult<()> {
+        let event = self
+            .sdk
+            .request(CreateGroup {
+                user_id: self.user_id,
+                group_name: name.into(),
+                group_type,
+            })
+            .await?;
+
+        self.enter(event.group_info).await
+    }
+
+    /// Enter an existing group
+    pub async fn join(&self, group_id: impl Into<String>) -> SdkResult<()> {
+        let info = self
+            .sdk
+            .request(EnterGroup {
+                user_id: self.user_id,
+                group_id: group_id.into(),
+            })
+            .await?;
+
+        self.enter(info).await
+    }
+
+    /// Leave the current group
+    pub async fn leave(&self) -> SdkResult<()> {
+        self.sdk
+            .request(LeaveGroup {
+                user_id: self.user_id,
+            })
+            .await?;
+
+        if let Some(state) = self.state.lock().await.take() {
+            self.safety.forget_group(&state.info.group_id).await;
+        }
+
+        Ok(())
+    }
+
+    /// Invite users to the current group
+    pub async fn invite(&self, users: Vec<u64>) -> SdkResult<()> {
+        self.require(|info| info.can_invite_new_members, "invite new members")
+            .await?;
+
+        self.sdk
+            .request(InviteUsersToGroup {
+                user_id: self.user_id,
+                friend_id: users,
+            })
+            .await?;
+
+        Ok(())
+    }
+
+    /// Remove users from the current group
+    pub async fn kick(&self, users: Vec<u64>) -> SdkResult<()> {
+        self.require(|info| info.can_remove_members, "remove members")
+            .await?;
+
+        self.sdk
+            .request(RemoveUsersFromGroup {
+                user_id: self.user_id,
+                friend_id: users,
+            })
+            .await?;
+
+        Ok(())
+    }
+
+    /// Invite users to the game session of the current group
+    pub async fn send_game_invite(
+        &self,
+        users: Vec<u64>,
+        message: impl Into<String>,
+    ) -> SdkResult<()> {
+        self.require(|info| info.can_send_game_invites, "send game invites")
+            .await?;
+
+        self.sdk
+            .request(SendGroupGameInvite {
+                user_id: self.user_id,
+                message: message.into(),
+                invitees: users,
+            })
+            .await?;
+
+        Ok(())
+    }
+
+    /// Accept the invite sent by a user
+    ///
+    /// Group invites enter the group, game invites are accepted with [`AcceptInvite`].
+    pub async fn accept_invite(&self, from: u64) -> SdkResult<()> {
+        let invite = self
+            .take_invite(from)
+            .await
+            .ok_or_else(|| SdkError::Other(format!("No pending invite from {}", from)))?;
+
+        match invite.kind {
+            InviteKind::Group { .. } => self.join(invite.group_id).await,
+            InviteKind::Game { .. } => {
+                self.sdk
+                    .request(AcceptInvite {
+                        user_id: self.user_id,
+                        other_id: from,
+                    })
+                    .await?;
+
+                Ok(())
+            }
+        }
+    }
+
+    /// Forget the invite sent by a user
+    pub async fn decline_invite(&self, from: u64) -> Option<PartyInvite> {
+        self.take_invite(from).await
+    }
+
+    /// Turn VoIP on or off
+    pub async fn enable_voip(&self, enable: bool) -> SdkResult<()> {
+        self.sdk.request(EnableVoip { enable }).await?;
+        Ok(())
+    }
+
+    /// Mute or unmute a member of the current group through [`Safety::mute`]
+    pub async fn mute(&self, user_id: u64, mute: bool) -> SdkResult<()> {
+        let group_id = self.group_id().await?;
+        self.safety.mute(&group_id, user_id, mute).await
+    }
+
+    /// Query the mute state of every member through [`Safety::refresh_mute_state`]
+    pub async fn refresh_mute_state(&self) -> SdkResult<()> {
+        let group_id = self.group_id().await?;
+        self.safety.refresh_mute_state(&group_id).await
+    }
+
+    /// Query the members of the current group from the server
+    pub async fn refresh_members(&self) -> SdkResult<()> {
+        let group_id = self.group_id().await?;
+        let response = self
+            .sdk
+            .request(QueryGroup {
+                user_id: self.user_id,
+                group_id,
+            })
+            .await?;
+
+        self.set_members(response.members).await;
+        Ok(())
+    }
+
+    /// Feed an event received from the server into the party
+    pub async fn handle_event(&self, event: &EventBody) {
+        match event {
+            EventBody::GroupEnterEvent(event) => {
+                if let Err(err) = self.enter(event.group_info.clone()).await {
+                    error!("Failed to load party members: {}", err);
+                }
+            }
+            EventBody::GroupEvent(event) => self.set_members(event.members.clone()).await,
+            EventBody::GroupLeaveEvent(event) => {
+                let mut state = self.state.lock().await;
+                if state
+                    .as_ref()
+                    .is_some_and(|state| state.info.group_id == event.group_id)
+                {
+                    debug!("Left group {}", event.group_id);
+                    *state = None;
+                }
+            }
+            EventBody::GroupInviteEvent(event) => {
+                self.add_invite(PartyInvite {
+                    from: event.from_id,
+                    group_id: event.group_id.clone(),
+                    group_name: event.group_name.clone(),
+                    kind: InviteKind::Group {
+                        group_type: event.group_type,
+                    },
+                })
+                .await;
+            }
+            EventBody::MultiplayerInvite(event) => {
+                self.add_invite(PartyInvite {
+                    from: event.from,
+                    group_id: event.group_id.clone(),
+                    group_name: event.group_name.clone(),
+                    kind: InviteKind::Game {
+                        multiplayer_id: event.multiplayer_id.clone(),
+                        session_information: event.session_information.clone(),
+                    },
+                })
+                .await;
+            }
+            _ => {}
+        }
+    }
+
+    async fn enter(&self, info: GroupInfo) -> SdkResult<()> {
+        debug!("Entered group {} ({})", info.group_name, info.group_id);
+
+        *self.state.lock().await = Some(PartyState {
+            info,
+            members: HashMap::new(),
+        });
+
+        self.refresh_members().await
+    }
+
+    async fn set_members(&self, members: Vec<Friend>) {
+        if let Some(state) = self.state.lock().await.as_mut() {
+            state.set_members(members);
+        }
+    }
+
+    async fn add_invite(&self, invite: PartyInvite) {
+        let blocked = self.safety.blocked_ids().await;
+        let from = invite.from;
+
+        if !push_invite(&mut *self.invites.lock().await, invite, &blocked) {
+            debug!("Dropped invite from blocked user {}", from);
+        }
+    }
+
+    async fn take_invite(&self, from: u64) -> Option<PartyInvite> {
+        remove_invite(&mut *self.invites.lock().await, from)
+    }
+
+    async fn group_id(&self) -> SdkResult<String> {
+        self.state
+            .lock()
+            .await
+            .as_ref()
+            .map(|state| state.info.group_id.clone())
+            .ok_or_else(|| SdkError::Other("Not in a party".to_string()))
+    }
+
+    async fn require(
+        &self,
+        allowed: impl FnOnce(&GroupInfo) -> bool,
+        action: &str,
+    ) -> SdkResult<()> {
+        check_allowed(self.state.lock().await.as_ref(), allowed, action)
+    }
+}
+
+impl PartyState {
+    fn set_members(&mut self, members: Vec<Friend>) {
+        self.members = members
+            .into_iter()
+            .map(|friend| (friend.user_id, friend))
+            .collect();
+    }
+}
+
+/// Keep only the latest invite of every sender, returns false if the sender is blocked
+fn push_invite(
+    invites: &mut Vec<PartyInvite>,
+    invite: PartyInvite,
+    blocked: &HashSet<u64>,
+) -> bool {
+    if blocked.contains(&invite.from) {
+        return false;
+    }
+
+    invites.retain(|pending| pending.from != invite.from);
+    invites.push(invite);
+    true
+}
+
+fn remove_invite(invites: &mut Vec<PartyInvite>, from: u64) -> Option<PartyInvite> {
+    let index = invites.iter().position(|invite| invite.from == from)?;
+    Some(invites.remove(index))
+}
+
+/// Fails unless the user is in a group that allows the action
+fn check_allowed(
+    state: Option<&PartyState>,
+    allowed: impl FnOnce(&GroupInfo) -> bool,
+    action: &str,
+) -> SdkResult<()> {
+    let Some(state) = state else {
+        return Err(SdkError::Other("Not in a party".to_string()));
+    };
+
+    if !allowed(&state.info) {
+        return Err(SdkError::Other(format!(
+            "Not allowed to {} in this group",
+            action
+        )));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::{friends::FriendState, presence::Presence};
+
+    fn friend(user_id: u64) -> Friend {
+        Friend {
+            user_id,
+            persona_id: user_id,
+            persona: format!("user{}", user_id),
+            avatar_id: String::new(),
+            group: String::new(),
+            group_id: String::new(),
+            presence: Presence::Online,
+            state: FriendState::Mutual,
+            title_id: String::new(),
+            title: String::new(),
+            multiplayer_id: String::new(),
+            rich_presence: String::new(),
+            game_presence: String::new(),
+        }
+    }
+
+    fn invite(from: u64, group_id: &str) -> PartyInvite {
+        PartyInvite {
+            from,
+            group_id: group_id.to_string(),
+            group_name: String::new(),
+            kind: InviteKind::Group {
+                group_type: GroupType::Private,
+            },
+        }
+    }
+
+    fn state(can_invite_new_members: bool) -> PartyState {
+        PartyState {
+            info: GroupInfo {
+                group_name: "party".to_string(),
+                group_id: "1".to_string(),
+                group_type: GroupType::Private,
+                can_invite_new_members,
+                can_remove_members: false,
+                can_send_game_invites: false,
+                max_group_size: GroupInfo::default_max_group_size(),
+            },
+            members: HashMap::new(),
+        }
+    }
+
+    #[test]
+    fn test_invite_bookkeeping() {
+        let mut invites = Vec::new();
+
+        let blocked = HashSet::new();
+
+        push_invite(&mut invites, invite(1, "a"), &blocked);
+        push_invite(&mut invites, invite(2, "b"), &blocked);
+        // A later invite from the same sender replaces the earlier one
+        push_invite(&mut invites, invite(1, "c"), &blocked);
+        assert_eq!(invites.len(), 2);
+
+        let taken = remove_invite(&mut invites, 1).unwrap();
+        assert_eq!(taken.group_id, "c");
+        assert!(remove_invite(&mut invites, 1).is_none());
+        assert_eq!(invites.len(), 1);
+    }
+
+    #[test]
+    fn test_invites_from_blocked_users() {
+        let mut invites = vec![invite(1, "a")];
+        let blocked = HashSet::from([1]);
+
+        assert!(!push_invite(&mut invites, invite(1, "b"), &blocked));
+        assert!(!push_invite(&mut invites, invite(1, "c"), &blocked));
+        assert!(push_invite(&mut invites, invite(2, "d"), &blocked));
+
+        let groups: Vec<&str> = invites
+            .iter()
+            .map(|invite| invite.group_id.as_str())
+            .collect();
+        assert_eq!(groups, vec!["a", "d"]);
+    }
+
+    #[test]
+    fn test_check_allowed() {
+        let allowed = |info: &GroupInfo| info.can_invite_new_members;
+
+        assert!(check_allowed(None, allowed, "invite new members").is_err());
+        assert!(check_allowed(Some(&state(false)), allowed, "invite new members").is_err());
+        assert!(check_allowed(Some(&state(true)), allowed, "invite new members").is_ok());
+    }
+
+    #[test]
+    fn test_set_members() {
+        let mut state = state(true);
+
+        state.set_members(vec![friend(1), friend(2)]);
+        state.set_members(vec![friend(2), friend(3)]);
+
+        let mut members: Vec<u64> = state.members.keys().copied().collect();
+        members.sort();
+        assert_eq!(members, vec![2, 3]);
+    }
+}
@@ -0,0 +1,137 @@
+use std::{collections::HashMap, sync::Arc};
+
+use tokio::sync::Mutex;
+use tracing::debug;
+
+use crate::{
+    protocol::{
+        friends::{FriendState, QueryAreFriends},
+        permissions::{Access, CheckPermission, Permission},
+        EventBody,
+    },
+    sdk::{OriginSdk, SdkResult},
+    services::current_user_ids,
+};
+
+/// Permission checks for parental-control compliance
+///
+/// Results of [`CheckPermission`] are cached per user and permission until a
+/// [`EventBody::ProfileEvent`] for the user or a [`EventBody::Login`] is received.
+/// Checks for the current user always apply to the user logged in at that time.
+pub struct Permissions {
+    sdk: Arc<OriginSdk>,
+    cache: Mutex<HashMap<(u64, Permission), Access>>,
+}
+
+impl Permissions {
+    /// Create the permission service for the current user
+    pub async fn new(sdk: Arc<OriginSdk>) -> SdkResult<Self> {
+        // Fail early if no user is logged in
+        current_user_ids(&sdk).await?;
+
+        Ok(Self {
+            sdk,
+            cache: Mutex::new(HashMap::new()),
+        })
+    }
+
+    /// Access of a user to a permission
+    pub async fn check(&self, user_id: u64, permission: Permission) -> SdkResult<Access> {
+        if let Some(access) = self.cache.lock().await.get(&(user_id, permission)) {
+            return Ok(*access);
+        }
+
+        let response = self
+            .sdk
+            .request(CheckPermission {
+                user_id,
+                permission_id: permission,
+            })
+            .await?;
+
+        debug!(
+            "User {} has {:?} access to {:?}",
+            user_id, response.access, permission
+        );
+
+        self.cache
+            .lock()
+            .await
+            .insert((user_id, permission), response.access);
+
+        Ok(response.access)
+    }
+
+    /// Returns true if the current user may play online with another user
+    ///
+    /// [`Access::FriendsOnly`] is resolved with [`QueryAreFriends`].
+    pub async fn can_play_with(&self, user_id: u64) -> SdkResult<bool> {
+        let current = self.user_id().await?;
+        let access = self.check(current, Permission::Multiplayer).await?;
+
+        if access != Access::FriendsOnly {
+            return Ok(access.allows(false));
+        }
+
+        let response = self
+            .sdk
+            .request(QueryAreFriends {
+                user_id: current,
+                friends: vec![user_id],
+            })
+            .await?;
+
+        let is_friend = response
+            .users
+            .iter()
+            .any(|status| status.friend_id == user_id && status.state == FriendState::Mutual);
+
+        Ok(access.allows(is_friend))
+    }
+
+    /// Returns true if the current user may play online at all
+    pub async fn can_play_online(&self) -> SdkResult<bool> {
+        let access = self
+            .check(self.user_id().await?, Permission::Multiplayer)
+            .await?;
+        Ok(matches!(access, Access::Granted | Access::FriendsOnly))
+    }
+
+    /// Returns true if the current user may make purchases
+    pub async fn can_purchase(&self) -> SdkResult<bool> {
+        let access = self
+            .check(self.user_id().await?, Permission::Purchase)
+            .await?;
+        Ok(access.allows(false))
+    }
+
+    /// Returns true if the current user may play trials
+    pub async fn can_play_trial(&self) -> SdkResult<bool> {
+        let access = self.check(self.user_id().await?, Permission::Trial).await?;
+        Ok(access.allows(false))
+    }
+
+    /// Drop every cached result
+    pub async fn invalidate(&self) {
+        self.cache.lock().await.clear();
+    }
+
+    /// Feed an event received from the server into the cache
+    pub async fn handle_event(&self, event: &EventBody) {
+        match event {
+            EventBody::ProfileEvent(event) => {
+                self.cache
+                    .lock()
+                    .await
+                    .retain(|(user_id, _), _| *user_id != event.user_id);
+            }
+            EventBody::Login(_) => self.invalidate().await,
+            _ => {}
+        }
+    }
+
+    /// Id of the current user, resolved again after a [`EventBody::Login`]
+    async fn user_id(&self) -> SdkResult<u64> {
+        Ok(current_user_ids(&self.sdk).await?.0)
+    }
+}
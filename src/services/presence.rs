@@ -0,0 +1,432 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex as StdMutex},
+    time::Duration,
+};
+
+use tokio::{sync::Mutex, task::JoinHandle};
+use tracing::{debug, error};
+
+use crate::{
+    protocol::{
+        friends::Friend,
+        presence::{
+            CurrentUserPresenceEvent, GetPresenceResponse, Presence, QueryPresence, SetPresence,
+            SubscribePresence, UnsubscribePresence,
+        },
+        EventBody,
+    },
+    sdk::{OriginSdk, SdkResult},
+    services::current_user_ids,
+};
+
+/// Default delay between the last local change and publishing it
+pub const DEFAULT_PRESENCE_DEBOUNCE: Duration = Duration::from_millis(500);
+
+/// Presence the game wants to publish for the current user
+#[derive(Debug, Clone, PartialEq)]
+pub struct LocalPresence {
+    pub presence: Presence,
+    pub rich_presence: String,
+    pub game_presence: String,
+    pub session_id: String,
+}
+
+impl Default for LocalPresence {
+    fn default() -> Self {
+        Self {
+            presence: Presence::Ingame,
+            rich_presence: String::new(),
+            game_presence: String::new(),
+            session_id: String::new(),
+        }
+    }
+}
+
+/// Last known presence of a user
+#[derive(Debug, Clone, PartialEq)]
+pub struct UserPresence {
+    pub presence: Presence,
+    pub title: String,
+    pub title_id: String,
+    pub multiplayer_id: String,
+    pub rich_presence: String,
+    pub game_presence: String,
+    pub group: String,
+    pub group_id: String,
+}
+
+impl From<&Friend> for UserPresence {
+    fn from(friend: &Friend) -> Self {
+        Self {
+            presence: friend.presence,
+            title: friend.title.clone(),
+            title_id: friend.title_id.clone(),
+            multiplayer_id: friend.multiplayer_id.clone(),
+            rich_presence: friend.rich_presence.clone(),
+            game_presence: friend.game_presence.clone(),
+            group: friend.group.clone(),
+            group_id: friend.group_id.clone(),
+        }
+    }
+}
+
+impl From<&GetPresenceResponse> for UserPresence {
+    fn from(response: &GetPresenceResponse) -> Self {
+        Self {
+            presence: response.presence,
+            title: response.title.clone(),
+            title_id: response.title_id.clone(),
+            multiplayer_id: response.multiplayer_id.clone(),
+            rich_presence: response.rich_presence.clone(),
+            game_presence: response.game_presence.clone(),
+            group: response.group.clone(),
+            group_id: response.group_id.clone(),
+        }
+    }
+}
+
+impl From<&CurrentUserPresenceEvent> for UserPresence {
+    fn from(event: &CurrentUserPresenceEvent) -> Self {
+        Self {
+            presence: event.presence,
+            title: event.title.clone(),
+            title_id: event.title_id.clone(),
+            multiplayer_id: event.multiplayer_id.clone(),
+            rich_presence: event.rich_presence.clone(),
+            game_presence: event.game_presence.clone(),
+            group: event.group.clone(),
+            group_id: event.group_id.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct LocalState {
+    desired: Option<LocalPresence>,
+    /// Bumped on every local change, so that only the last scheduled publish is sent
+    generation: u64,
+}
+
+impl LocalState {
+    /// Replace the desired presence, returns the generation to publish or `None`
+    /// if nothing changed
+    fn change(&mut self, presence: LocalPresence) -> Option<u64> {
+        if self.desired.as_ref() == Some(&presence) {
+            return None;
+        }
+
+        self.desired = Some(presence);
+        Some(self.invalidate())
+    }
+
+    /// Supersede the scheduled publish, returns the new generation
+    fn invalidate(&mut self) -> u64 {
+        self.generation += 1;
+        self.generation
+    }
+
+    /// Presence to publish for a scheduled generation, `None` if a later change
+    /// superseded it
+    fn due(&self, generation: u64) -> Option<LocalPresence> {
+        if self.generation != generation {
+            return None;
+        }
+
+        self.desired.clone()
+    }
+}
+
+/// Presence manager for the current user
+///
+/// Holds the presence the game wants to show, publishes it with [`SetPresence`] after
+/// a debounce delay, coalescing changes made within the delay, and publishes it again
+/// after a [`EventBody::Login`] or when the user comes back online. Also keeps the
+/// presence of subscribed users up to date, following every [`EventBody::PresenceEvent`]
+/// with a [`QueryPresence`] request. Requests are always sent as the user logged in at
+/// that time.
+pub struct PresenceManager {
+    sdk: Arc<OriginSdk>,
+    debounce: Duration,
+    local: Arc<Mutex<LocalState>>,
+    current: Mutex<Option<UserPresence>>,
+    subscribed: Mutex<HashMap<u64, Option<UserPresence>>>,
+    /// Publish waiting for its debounce delay, never held across an await
+    pending: StdMutex<Option<JoinHandle<()>>>,
+}
+
+impl Drop for PresenceManager {
+    fn drop(&mut self) {
+        let pending = self
+            .pending
+            .get_mut()
+            .unwrap_or_else(|err| err.into_inner());
+        if let Some(pending) = pending.take() {
+            pending.abort();
+        }
+    }
+}
+
+impl PresenceManager {
+    /// Create a presence manager for the current user
+    pub async fn new(sdk: Arc<OriginSdk>, debounce: Duration) -> SdkResult<Self> {
+        // Fail early if no user is logged in
+        current_user_ids(&sdk).await?;
+
+        Ok(Self {
+            sdk,
+            debounce,
+            local: Arc::new(Mutex::new(LocalState::default())),
+            current: Mutex::new(None),
+            subscribed: Mutex::new(HashMap::new()),
+            pending: StdMutex::new(None),
+        })
+    }
+
+    /// Presence the game asked to publish
+    pub async fn desired(&self) -> Option<LocalPresence> {
+        self.local.lock().await.desired.clone()
+    }
+
+    /// Presence of the current user as last reported by the server
+    pub async fn current(&self) -> Option<UserPresence> {
+        self.current.lock().await.clone()
+    }
+
+    /// Replace the local presence and publish it once no other change follows
+    /// within the debounce delay
+    pub async fn set(&self, presence: LocalPresence) {
+        let mut local = self.local.lock().await;
+        let Some(generation) = local.change(presence) else {
+            return;
+        };
+
+        let sdk = self.sdk.clone();
+        let state = self.local.clone();
+        let debounce = self.debounce;
+
+        let handle = tokio::spawn(async move {
+            tokio::time::sleep(debounce).await;
+
+            let Some(desired) = state.lock().await.due(generation) else {
+                return;
+            };
+
+            if let Err(err) = Self::send(&sdk, desired).await {
+                error!("Failed to publish presence: {}", err);
+            }
+        });
+
+        self.replace_pending(Some(handle));
+    }
+
+    /// Change only the rich presence string of the local presence
+    pub async fn set_rich_presence(&self, rich_presence: impl Into<String>) {
+        let mut presence = self.desired().await.unwrap_or_default();
+        presence.rich_presence = rich_presence.into();
+        self.set(presence).await;
+    }
+
+    /// Publish the local presence immediately, skipping the debounce delay
+    pub async fn publish(&self) -> SdkResult<()> {
+        let desired = {
+            let mut local = self.local.lock().await;
+            // Cancel any publish that is still waiting for its debounce delay
+            local.invalidate();
+            self.replace_pending(None);
+            local.desired.clone()
+        };
+
+        match desired {
+            Some(desired) => Self::send(&self.sdk, desired).await,
+            None => Ok(()),
+        }
+    }
+
+    /// Subscribe to presence updates of the given users and query their current presence
+    pub async fn subscribe(&self, users: Vec<u64>) -> SdkResult<()> {
+        self.sdk
+            .request(SubscribePresence {
+                user_id: self.user_id().await?,
+                users: users.clone(),
+            })
+            .await?;
+
+        {
+            let mut subscribed = self.subscribed.lock().await;
+            for user in &users {
+                subscribed.entry(*user).or_insert(None);
+            }
+        }
+
+        self.query(users).await
+    }
+
+    /// Stop receiving presence updates of the given users
+    pub async fn unsubscribe(&self, users: Vec<u64>) -> SdkResult<()> {
+        self.sdk
+            .request(UnsubscribePresence {
+                user_id: self.user_id().await?,
+                users: users.clone(),
+            })
+            .await?;
+
+        let mut subscribed = self.subscribed.lock().await;
+        for user in &users {
+            subscribed.remove(user);
+        }
+
+        Ok(())
+    }
+
+    /// Last known presence of a subscribed user
+    pub async fn presence_of(&self, user_id: u64) -> Option<UserPresence> {
+        self.subscribed
+            .lock()
+            .await
+            .get(&user_id)
+            .cloned()
+            .flatten()
+    }
+
+    /// Last known presence of every subscribed user
+    pub async fn subscribed(&self) -> HashMap<u64, Option<UserPresence>> {
+        self.subscribed.lock().await.clone()
+    }
+
+    /// Feed an event received from the server into the manager
+    pub async fn handle_event(&self, event: &EventBody) {
+        match event {
+            EventBody::Login(login) if login.is_logged_in => {
+                // The presence may belong to the previous user
+                self.current.lock().await.take();
+                self.republish().await;
+            }
+            EventBody::OnlineStatusEvent(status) if status.is_online => self.republish().await,
+            EventBody::CurrentUserPresenceEvent(event) => {
+                *self.current.lock().await = Some(event.into());
+            }
+            EventBody::GetPresenceResponse(response) => {
+                self.update(response.user_id, response.into()).await;
+            }
+            EventBody::PresenceEvent(event) => {
+                if !self.subscribed.lock().await.contains_key(&event.userid) {
+                    return;
+                }
+
+                if let Err(err) = self.query(vec![event.userid]).await {
+                    error!("Failed to query presence of {}: {}", event.userid, err);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Abort the publish waiting for its debounce delay and track the new one
+    fn replace_pending(&self, handle: Option<JoinHandle<()>>) {
+        let mut pending = self.pending.lock().unwrap_or_else(|err| err.into_inner());
+        if let Some(previous) = std::mem::replace(&mut *pending, handle) {
+            previous.abort();
+        }
+    }
+
+    async fn republish(&self) {
+        debug!("Publishing presence again");
+
+        if let Err(err) = self.publish().await {
+            error!("Failed to publish presence: {}", err);
+        }
+    }
+
+    async fn query(&self, users: Vec<u64>) -> SdkResult<()> {
+        let response = self
+            .sdk
+            .request(QueryPresence {
+                user_id: self.user_id().await?,
+                users,
+            })
+            .await?;
+
+        for friend in &response.friends {
+            self.update(friend.user_id, friend.into()).await;
+        }
+
+        Ok(())
+    }
+
+    async fn update(&self, user_id: u64, presence: UserPresence) {
+        if self.user_id().await.ok() == Some(user_id) {
+            *self.current.lock().await = Some(presence);
+            return;
+        }
+
+        if let Some(entry) = self.subscribed.lock().await.get_mut(&user_id) {
+            *entry = Some(presence);
+        }
+    }
+
+    /// Id of the current user, resolved again after a [`EventBody::Login`]
+    async fn user_id(&self) -> SdkResult<u64> {
+        Ok(current_user_ids(&self.sdk).await?.0)
+    }
+
+    async fn send(sdk: &OriginSdk, desired: LocalPresence) -> SdkResult<()> {
+        let (user_id, _) = current_user_ids(sdk).await?;
+
+        sdk.request(SetPresence {
+            user_id,
+            presence: desired.presence,
+            rich_presence: desired.rich_presence,
+            game_presence: desired.game_presence,
+            session_id: desired.session_id,
+        })
+        .await?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn presence(rich_presence: &str) -> LocalPresence {
+        LocalPresence {
+            rich_presence: rich_presence.to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_debounce_coalesces_changes() {
+        let mut local = LocalState::default();
+
+        let first = local.change(presence("menu")).unwrap();
+        let second = local.change(presence("match")).unwrap();
+
+        // Only the last change within the delay is published
+        assert_eq!(local.due(first), None);
+        assert_eq!(local.due(second), Some(presence("match")));
+    }
+
+    #[test]
+    fn test_debounce_skips_unchanged() {
+        let mut local = LocalState::default();
+
+        let generation = local.change(presence("menu")).unwrap();
+        assert_eq!(local.change(presence("menu")), None);
+
+        // The scheduled publish is still valid
+        assert_eq!(local.due(generation), Some(presence("menu")));
+    }
+
+    #[test]
+    fn test_publish_cancels_debounce() {
+        let mut local = LocalState::default();
+
+        let generation = local.change(presence("menu")).unwrap();
+        local.invalidate();
+
+        assert_eq!(local.due(generation), None);
+    }
+}
@@ -0,0 +1,169 @@
+use std::{collections::HashSet, sync::Arc, time::Duration};
+
+use tokio::{sync::Mutex, task::JoinHandle};
+use tracing::{debug, warn};
+
+use crate::{
+    protocol::friends::AddRecentPlayers,
+    sdk::{OriginSdk, SdkResult},
+    services::{current_user_ids, safety::Safety},
+};
+
+struct Recorder {
+    sdk: Arc<OriginSdk>,
+    user_id: u64,
+    safety: Arc<Safety>,
+    pending: Mutex<Vec<u64>>,
+}
+
+impl Recorder {
+    async fn flush(&self) -> SdkResult<usize> {
+        let players = std::mem::take(&mut *self.pending.lock().await);
+        let batch = without_blocked(players, &self.safety.blocked_ids().await);
+
+        if batch.is_empty() {
+            return Ok(0);
+        }
+
+        let result = self
+            .sdk
+            .request(AddRecentPlayers {
+                user_id: self.user_id,
+                player: batch.clone(),
+            })
+            .await;
+
+        if let Err(err) = result {
+            // Keep the players for the next flush, along with any recorded meanwhile
+            queue_players(&mut *self.pending.lock().await, batch, self.user_id);
+
+            return Err(err);
+        }
+
+        debug!("Added {} recent players", batch.len());
+        Ok(batch.len())
+    }
+}
+
+/// Records the players the current user played with
+///
+/// Match participants are deduplicated and sent with [`AddRecentPlayers`] when the
+/// match ends and every `interval` in between. The current user and blocked users
+/// are left out. Players of a failed request are kept and sent with the next flush.
+/// The periodic flush stops when the recorder is dropped.
+pub struct RecentPlayers {
+    recorder: Arc<Recorder>,
+    handle: JoinHandle<()>,
+}
+
+impl Drop for RecentPlayers {
+    fn drop(&mut self) {
+        self.handle.abort();
+    }
+}
+
+impl RecentPlayers {
+    /// Start recording the recent players of the current user
+    pub async fn start(
+        sdk: Arc<OriginSdk>,
+        safety: Arc<Safety>,
+        interval: Duration,
+    ) -> SdkResult<Self> {
+        let (user_id, _) = current_user_ids(&sdk).await?;
+
+        let recorder = Arc::new(Recorder {
+            sdk,
+            user_id,
+            safety,
+            pending: Mutex::new(Vec::new()),
+        });
+
+        let handle = tokio::spawn(Self::run(recorder.clone(), interval));
+
+        Ok(Self { recorder, handle })
+    }
+
+    /// Record the participants of the current match
+    pub async fn record(&self, players: impl IntoIterator<Item = u64>) {
+        queue_players(
+            &mut *self.recorder.pending.lock().await,
+            players,
+            self.recorder.user_id,
+        );
+    }
+
+    /// Players waiting to be sent
+    pub async fn pending(&self) -> Vec<u64> {
+        self.recorder.pending.lock().await.clone()
+    }
+
+    /// Send the recorded players at the end of a match
+    pub async fn end_match(&self) -> SdkResult<usize> {
+        self.flush().await
+    }
+
+    /// Send the recorded players now, returns how many were sent
+    pub async fn flush(&self) -> SdkResult<usize> {
+        self.recorder.flush().await
+    }
+
+    async fn run(recorder: Arc<Recorder>, interval: Duration) {
+        let mut ticker = tokio::time::interval(interval);
+        ticker.tick().await;
+
+        loop {
+            ticker.tick().await;
+
+            if let Err(err) = recorder.flush().await {
+                warn!("Failed to add recent players, retrying later: {}", err);
+            }
+        }
+    }
+}
+
+/// Add players that are not queued yet, leaving out the current user
+fn queue_players(pending: &mut Vec<u64>, players: impl IntoIterator<Item = u64>, user_id: u64) {
+    for player in players {
+        if player != user_id && !pending.contains(&player) {
+            pending.push(player);
+        }
+    }
+}
+
+fn without_blocked(players: Vec<u64>, blocked: &HashSet<u64>) -> Vec<u64> {
+    players
+        .into_iter()
+        .filter(|player| !blocked.contains(player))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_queue_players() {
+        let mut pending = Vec::new();
+
+        queue_players(&mut pending, [2, 3, 2, 1], 1);
+        queue_players(&mut pending, [3, 4], 1);
+
+        assert_eq!(pending, vec![2, 3, 4]);
+    }
+
+    #[test]
+    fn test_requeue_failed_batch() {
+        // Recorded while the failed request was in flight
+        let mut pending = vec![4, 5];
+
+        queue_players(&mut pending, [2, 4], 1);
+
+        assert_eq!(pending, vec![4, 5, 2]);
+    }
+
+    #[test]
+    fn test_without_blocked() {
+        let blocked = HashSet::from([3]);
+        assert_eq!(without_blocked(vec![2, 3, 4], &blocked), vec![2, 4]);
+    }
+}
@@ -0,0 +1,261 @@
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+};
+
+use tokio::sync::Mutex;
+use tracing::{debug, error};
+
+use crate::{
+    protocol::{
+        friends::{Friend, FriendState},
+        user::{BlockUser, GetBlockList, UnblockUser, User},
+        voip::{EnumMuteState, MuteUser, QueryMuteState},
+        EventBody,
+    },
+    sdk::{OriginSdk, SdkResult},
+    services::current_user_ids,
+};
+
+/// Block list and mute state of the current user
+///
+/// Keeps the block list loaded with [`GetBlockList`] and reloads it on
+/// [`EventBody::BlockListUpdated`]. Events from blocked users should be dropped with
+/// [`Safety::allows`] before they reach the game. Friend requests from blocked users
+/// are dropped by the [`FriendsList`](crate::services::friends::FriendsList) built on
+/// top of this module. Mute state is tracked per group, updated from
+/// [`EventBody::VoipStatusEvent`] events, and shared with the
+/// [`Party`](crate::services::party::Party).
+pub struct Safety {
+    sdk: Arc<OriginSdk>,
+    user_id: u64,
+    blocked: Mutex<HashMap<u64, User>>,
+    mutes: Mutex<HashMap<String, HashMap<u64, EnumMuteState>>>,
+}
+
+impl Safety {
+    /// Create the safety module of the current user and load the block list
+    pub async fn new(sdk: Arc<OriginSdk>) -> SdkResult<Self> {
+        let (user_id, _) = current_user_ids(&sdk).await?;
+
+        let safety = Self {
+            sdk,
+            user_id,
+            blocked: Mutex::new(HashMap::new()),
+            mutes: Mutex::new(HashMap::new()),
+        };
+
+        safety.refresh_block_list().await?;
+        Ok(safety)
+    }
+
+    /// Reload the block list from the server
+    pub async fn refresh_block_list(&self) -> SdkResult<()> {
+        let response = self.sdk.request(GetBlockList).await?;
+
+        let blocked: HashMap<u64, User> = response
+            .user
+            .into_iter()
+            .map(|user| (user.user_id, user))
+            .collect();
+
+        debug!("Block list contains {} users", blocked.len());
+        *self.blocked.lock().await = blocked;
+
+        Ok(())
+    }
+
+    /// Snapshot of the block list
+    pub async fn blocked(&self) -> Vec<User> {
+        self.blocked.lock().await.values().cloned().collect()
+    }
+
+    /// Returns true if the user is blocked
+    pub async fn is_blocked(&self, user_id: u64) -> bool {
+        self.blocked.lock().await.contains_key(&user_id)
+    }
+
+    /// Block a user and reload the block list
+    pub async fn block(&self, user_id: u64) -> SdkResult<()> {
+        self.sdk
+            .request(BlockUser {
+                user_id: self.user_id,
+                user_id_to_block: user_id,
+            })
+            .await?;
+
+        self.refresh_block_list().await
+    }
+
+    /// Unblock a user and reload the block list
+    pub async fn unblock(&self, user_id: u64) -> SdkResult<()> {
+        self.sdk
+            .request(UnblockUser {
+                user_id: self.user_id,
+                user_id_to_unblock: user_id,
+            })
+            .await?;
+
+        self.refresh_block_list().await
+    }
+
+    /// Returns false if the event was sent by a blocked user and must not reach the game
+    ///
+    /// Covers chat messages and group and game invites.
+    pub async fn allows(&self, event: &EventBody) -> bool {
+        let from = match event {
+            EventBody::ChatMessageEvent(event) => event.from_id,
+            EventBody::GroupInviteEvent(event) => event.from_id,
+            EventBody::MultiplayerInvite(event) => event.from,
+            EventBody::MultiplayerInvitePending(event) => event.from,
+            _ => return true,
+        };
+
+        !self.is_blocked(from).await
+    }
+
+    /// Ids of the blocked users
+    pub(crate) async fn blocked_ids(&self) -> HashSet<u64> {
+        self.blocked.lock().await.keys().copied().collect()
+    }
+
+    /// Mute or unmute a user in a group
+    pub async fn mute(&self, group_id: &str, user_id: u64, mute: bool) -> SdkResult<()> {
+        self.sdk
+            .request(MuteUser {
+                b_mute: mute,
+                group_id: group_id.to_string(),
+                user_id,
+            })
+            .await?;
+
+        let mut mutes = self.mutes.lock().await;
+        let state = mutes
+            .entry(group_id.to_string())
+            .or_default()
+            .entry(user_id)
+            .or_insert(EnumMuteState::None);
+
+        *state = EnumMuteState::from_flags(mute, state.is_muted_remotely());
+        Ok(())
+    }
+
+    /// Query the mute state of every member of a group from the server
+    pub async fn refresh_mute_state(&self, group_id: &str) -> SdkResult<()> {
+        let response = self
+            .sdk
+            .request(QueryMuteState {
+                group_id: group_id.to_string(),
+            })
+            .await?;
+
+        let states = response
+            .mute_state_array
+            .into_iter()
+            .map(|mute| (mute.user_id, mute.state))
+            .collect();
+
+        self.mutes.lock().await.insert(group_id.to_string(), states);
+        Ok(())
+    }
+
+    /// Mute state of a user in a group, [`EnumMuteState::None`] if unknown
+    pub async fn mute_state(&self, group_id: &str, user_id: u64) -> EnumMuteState {
+        self.mutes
+            .lock()
+            .await
+            .get(group_id)
+            .and_then(|group| group.get(&user_id))
+            .copied()
+            .unwrap_or(EnumMuteState::None)
+    }
+
+    /// Mute state of every known member of a group
+    pub async fn group_mute_state(&self, group_id: &str) -> HashMap<u64, EnumMuteState> {
+        self.mutes
+            .lock()
+            .await
+            .get(group_id)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// Forget the mute state of a group, e.g. after leaving it
+    pub async fn forget_group(&self, group_id: &str) {
+        self.mutes.lock().await.remove(group_id);
+    }
+
+    /// Feed an event received from the server into the safety module
+    pub async fn handle_event(&self, event: &EventBody) {
+        match event {
+            EventBody::BlockListUpdated(_) => {
+                if let Err(err) = self.refresh_block_list().await {
+                    error!("Failed to refresh block list: {}", err);
+                }
+            }
+            EventBody::VoipStatusEvent(event) => {
+                // The event does not name the group, update every group the user is in
+                for group in self.mutes.lock().await.values_mut() {
+                    if let Some(state) = group.get_mut(&event.user_id) {
+                        *state = state.with_status(&event.status).unwrap_or(*state);
+                    }
+                }
+            }
+            EventBody::GroupLeaveEvent(event) => self.forget_group(&event.group_id).await,
+            _ => {}
+        }
+    }
+}
+
+/// Drop pending friend requests sent by blocked users
+pub(crate) fn without_blocked_requests(
+    friends: Vec<Friend>,
+    blocked: &HashSet<u64>,
+) -> Vec<Friend> {
+    friends
+        .into_iter()
+        .filter(|friend| friend.state != FriendState::Request || !blocked.contains(&friend.user_id))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::presence::Presence;
+
+    fn friend(user_id: u64, state: FriendState) -> Friend {
+        Friend {
+            user_id,
+            persona_id: user_id,
+            persona: format!("user{}", user_id),
+            avatar_id: String::new(),
+            group: String::new(),
+            group_id: String::new(),
+            presence: Presence::Online,
+            state,
+            title_id: String::new(),
+            title: String::new(),
+            multiplayer_id: String::new(),
+            rich_presence: String::new(),
+            game_presence: String::new(),
+        }
+    }
+
+    #[test]
+    fn test_without_blocked_requests() {
+        let friends = vec![
+            friend(1, FriendState::Request),
+            friend(2, FriendState::Request),
+            friend(3, FriendState::Mutual),
+        ];
+        let blocked = HashSet::from([2, 3]);
+
+        let kept: Vec<u64> = without_blocked_requests(friends, &blocked)
+            .into_iter()
+            .map(|friend| friend.user_id)
+            .collect();
+
+        // Only requests are dropped, existing friendships are left alone
+        assert_eq!(kept, vec![1, 3]);
+    }
+}
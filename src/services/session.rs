@@ -0,0 +1,340 @@
+use std::sync::Arc;
+
+use tokio::sync::{broadcast, watch};
+use tracing::info;
+
+use crate::{
+    protocol::{
+        auth::{GetAuthToken, LoginReasonCode, Logout},
+        presence::GoOnline,
+        system::GetInternetConnectedState,
+        EventBody,
+    },
+    sdk::{OriginSdk, SdkError, SdkResult},
+};
+
+/// State of the user session
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SessionState {
+    /// The user logged out of the client
+    LoggedOut,
+    /// The user is logged in but the client is in offline mode
+    Offline,
+    Online,
+    /// The client failed to refresh its access token, backend requests will fail
+    TokenRefreshFailed,
+}
+
+/// A change of the session state
+#[derive(Debug, Clone)]
+pub struct SessionTransition {
+    pub from: SessionState,
+    pub to: SessionState,
+    /// Reason reported by the [`EventBody::Login`] event that caused the transition
+    pub reason: Option<LoginReasonCode>,
+}
+
+impl SessionTransition {
+    /// Returns true if data cached for the user may be stale after this transition
+    ///
+    /// The user may have changed after logging out and back in, and nothing fetched
+    /// while the access token was invalid can be trusted.
+    pub fn invalidates_caches(&self) -> bool {
+        matches!(
+            (self.from, self.to),
+            (_, SessionState::LoggedOut)
+                | (SessionState::LoggedOut, _)
+                | (SessionState::TokenRefreshFailed, _)
+        )
+    }
+}
+
+/// Input of the session state machine
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SessionInput {
+    /// A [`EventBody::Login`] event
+    Login {
+        logged_in: bool,
+        reason: LoginReasonCode,
+    },
+    /// Connectivity reported by [`EventBody::OnlineStatusEvent`] or
+    /// [`GetInternetConnectedState`]
+    Connected(bool),
+    /// A new access token was obtained
+    TokenRefreshed { connected: bool },
+    /// The user was logged out with [`Logout`]
+    Logout,
+}
+
+/// State after an input
+///
+/// Connectivity changes alone never leave [`SessionState::LoggedOut`] or
+/// [`SessionState::TokenRefreshFailed`], that takes a login or a new access token.
+fn next_state(current: SessionState, input: SessionInput) -> SessionState {
+    let connectivity = |connected| {
+        if connected {
+            SessionState::Online
+        } else {
+            SessionState::Offline
+        }
+    };
+
+    match input {
+        SessionInput::Login {
+            reason: LoginReasonCode::AccesstokenRefreshError,
+            ..
+        } => SessionState::TokenRefreshFailed,
+        SessionInput::Login {
+            logged_in: false, ..
+        } => SessionState::LoggedOut,
+        SessionInput::Login {
+            reason: LoginReasonCode::NetworkError,
+            ..
+        } => SessionState::Offline,
+        SessionInput::Login { .. } => SessionState::Online,
+        SessionInput::Connected(_)
+            if matches!(
+                current,
+                SessionState::LoggedOut | SessionState::TokenRefreshFailed
+            ) =>
+        {
+            current
+        }
+        SessionInput::Connected(connected) => connectivity(connected),
+        SessionInput::TokenRefreshed { .. } if current == SessionState::LoggedOut => current,
+        SessionInput::TokenRefreshed { connected } => connectivity(connected),
+        SessionInput::Logout => SessionState::LoggedOut,
+    }
+}
+
+/// User session state machine
+///
+/// Driven by [`EventBody::Login`] and [`EventBody::OnlineStatusEvent`] events and by the
+/// [`GoOnline`], [`Logout`], [`GetAuthToken`] and [`GetInternetConnectedState`]
+/// requests. Every transition is published to [`Session::transitions`] subscribers,
+/// so dependent subsystems can drop the data they cached for the user. Transitions
+/// that invalidate caches also drop the profile cached by [`OriginSdk`].
+pub struct Session {
+    sdk: Arc<OriginSdk>,
+    state: watch::Sender<SessionState>,
+    transitions: broadcast::Sender<SessionTransition>,
+}
+
+impl Session {
+    /// Create the session of the user logged in during the handshake
+    pub async fn new(sdk: Arc<OriginSdk>) -> SdkResult<Self> {
+        let (state, _) = watch::channel(SessionState::Offline);
+        let (transitions, _) = broadcast::channel(16);
+
+        let session = Self {
+            sdk,
+            state,
+            transitions,
+        };
+
+        session.check_connection().await?;
+        Ok(session)
+    }
+
+    /// Current session state
+    pub fn state(&self) -> SessionState {
+        *self.state.borrow()
+    }
+
+    /// Subscribe to the session state
+    pub fn subscribe(&self) -> watch::Receiver<SessionState> {
+        self.state.subscribe()
+    }
+
+    /// Subscribe to session state transitions
+    pub fn transitions(&self) -> broadcast::Receiver<SessionTransition> {
+        self.transitions.subscribe()
+    }
+
+    /// Ask the client whether it is connected to the internet and update the state
+    pub async fn check_connection(&self) -> SdkResult<SessionState> {
+        let connected = self.is_connected().await?;
+        self.apply(SessionInput::Connected(connected), None).await;
+
+        Ok(self.state())
+    }
+
+    /// Ask the client for a new access token
+    ///
+    /// Leaves [`SessionState::TokenRefreshFailed`] once a token is obtained.
+    pub async fn refresh_token(&self) -> SdkResult<SessionState> {
+        let token = self.sdk.request(GetAuthToken).await?;
+
+        if token.value.is_empty() {
+            return Err(SdkError::Other(
+                "Received an empty access token".to_string(),
+            ));
+        }
+
+        let connected = self.is_connected().await?;
+        self.apply(SessionInput::TokenRefreshed { connected }, None)
+            .await;
+
+        Ok(self.state())
+    }
+
+    /// Ask the client to go online, the state changes once the client reports it
+    pub async fn go_online(&self) -> SdkResult<()> {
+        self.sdk.request(GoOnline).await?;
+        Ok(())
+    }
+
+    /// Log the user out of the client
+    pub async fn logout(&self) -> SdkResult<()> {
+        self.sdk.request(Logout { user_index: 0 }).await?;
+        self.apply(SessionInput::Logout, None).await;
+        Ok(())
+    }
+
+    /// Wait until the session is online
+    pub async fn wait_online(&self) -> SdkResult<()> {
+        let mut state = self.state.subscribe();
+
+        state
+            .wait_for(|state| *state == SessionState::Online)
+            .await
+            .map_err(|_| SdkError::Other("Session closed".to_string()))?;
+
+        Ok(())
+    }
+
+    /// Feed an event received from the server into the state machine
+    pub async fn handle_event(&self, event: &EventBody) {
+        match event {
+            EventBody::Login(login) => {
+                let input = SessionInput::Login {
+                    logged_in: login.is_logged_in,
+                    reason: login.login_reason_code,
+                };
+
+                self.apply(input, Some(login.login_reason_code)).await;
+            }
+            EventBody::OnlineStatusEvent(status) => {
+                self.apply(SessionInput::Connected(status.is_online), None)
+                    .await;
+            }
+            _ => {}
+        }
+    }
+
+    async fn is_connected(&self) -> SdkResult<bool> {
+        let response = self.sdk.request(GetInternetConnectedState).await?;
+        Ok(response.connected != 0)
+    }
+
+    async fn apply(&self, input: SessionInput, reason: Option<LoginReasonCode>) {
+        let mut from = SessionState::LoggedOut;
+        let mut to = SessionState::LoggedOut;
+        self.state.send_modify(|state| {
+            from = *state;
+            to = next_state(from, input);
+            *state = to;
+        });
+
+        if from == to {
+            return;
+        }
+
+        info!("Session changed from {:?} to {:?}", from, to);
+        let transition = SessionTransition { from, to, reason };
+
+        if transition.invalidates_caches() {
+            self.sdk.clear_profile().await;
+        }
+
+        let _ = self.transitions.send(transition);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use LoginReasonCode::*;
+    use SessionState::*;
+
+    fn login(logged_in: bool, reason: LoginReasonCode) -> SessionInput {
+        SessionInput::Login { logged_in, reason }
+    }
+
+    #[test]
+    fn test_next_state() {
+        let cases = [
+            (Offline, login(true, UserInitiated), Online),
+            (Online, login(true, NetworkError), Offline),
+            (Online, login(false, UserInitiated), LoggedOut),
+            (
+                Online,
+                login(true, AccesstokenRefreshError),
+                TokenRefreshFailed,
+            ),
+            (
+                Online,
+                login(false, AccesstokenRefreshError),
+                TokenRefreshFailed,
+            ),
+            (LoggedOut, login(true, AlreadyOnline), Online),
+            (TokenRefreshFailed, login(true, UserInitiated), Online),
+            (Online, SessionInput::Connected(false), Offline),
+            (Offline, SessionInput::Connected(true), Online),
+            (LoggedOut, SessionInput::Connected(true), LoggedOut),
+            (
+                TokenRefreshFailed,
+                SessionInput::Connected(true),
+                TokenRefreshFailed,
+            ),
+            (
+                TokenRefreshFailed,
+                SessionInput::Connected(false),
+                TokenRefreshFailed,
+            ),
+            (
+                TokenRefreshFailed,
+                SessionInput::TokenRefreshed { connected: true },
+                Online,
+            ),
+            (
+                TokenRefreshFailed,
+                SessionInput::TokenRefreshed { connected: false },
+                Offline,
+            ),
+            (
+                LoggedOut,
+                SessionInput::TokenRefreshed { connected: true },
+                LoggedOut,
+            ),
+            (Online, SessionInput::Logout, LoggedOut),
+            (TokenRefreshFailed, SessionInput::Logout, LoggedOut),
+        ];
+
+        for (current, input, expected) in cases {
+            assert_eq!(
+                next_state(current, input),
+                expected,
+                "{:?} + {:?}",
+                current,
+                input
+            );
+        }
+    }
+
+    #[test]
+    fn test_invalidates_caches() {
+        let transition = |from, to| SessionTransition {
+            from,
+            to,
+            reason: None,
+        };
+
+        assert!(transition(Online, LoggedOut).invalidates_caches());
+        assert!(transition(LoggedOut, Online).invalidates_caches());
+        assert!(transition(TokenRefreshFailed, Online).invalidates_caches());
+        assert!(!transition(Online, Offline).invalidates_caches());
+        assert!(!transition(Offline, Online).invalidates_caches());
+    }
+}
@@ -0,0 +1,90 @@
+use std::sync::Arc;
+
+use crate::{
+    protocol::system::{
+        Environment, GetEnvironment, GetSetting, GetSettings, GetSettingsResponse, LanguageTag,
+        Setting,
+    },
+    sdk::{OriginSdk, SdkError, SdkResult},
+};
+
+/// Typed access to the client settings
+///
+/// [`GetSetting`] returns every value as a string, this parses them into booleans,
+/// [`LanguageTag`]s and [`Environment`]s.
+pub struct Settings {
+    sdk: Arc<OriginSdk>,
+}
+
+impl Settings {
+    pub fn new(sdk: Arc<OriginSdk>) -> Self {
+        Self { sdk }
+    }
+
+    /// Every setting at once
+    pub async fn all(&self) -> SdkResult<GetSettingsResponse> {
+        self.sdk.request(GetSettings).await
+    }
+
+    /// Active language of the client
+    pub async fn language(&self) -> SdkResult<LanguageTag> {
+        let value = self.raw(Setting::Language).await?;
+
+        // The setting may list several languages, the first one is the active one
+        let active = value.split(',').next().unwrap_or_default();
+
+        LanguageTag::parse(active)
+            .ok_or_else(|| SdkError::Other(format!("Invalid language tag: {}", value)))
+    }
+
+    /// Backend environment the client is connected to
+    pub async fn environment(&self) -> SdkResult<Environment> {
+        let response = self.sdk.request(GetEnvironment).await?;
+        Ok(Environment::parse(&response.environment))
+    }
+
+    pub async fn is_igo_available(&self) -> SdkResult<bool> {
+        self.flag(Setting::IsIgoAvailable).await
+    }
+
+    pub async fn is_igo_enabled(&self) -> SdkResult<bool> {
+        self.flag(Setting::IsIgoEnabled).await
+    }
+
+    pub async fn is_telemetry_enabled(&self) -> SdkResult<bool> {
+        self.flag(Setting::IsTelemetryEnabled).await
+    }
+
+    /// Returns true if the user put the client in offline mode
+    pub async fn is_manual_offline(&self) -> SdkResult<bool> {
+        self.flag(Setting::IsManualOffline).await
+    }
+
+    /// Raw value of a setting
+    pub async fn raw(&self, setting: Setting) -> SdkResult<String> {
+        let response = self
+            .sdk
+            .request(GetSetting {
+                setting_id: setting,
+            })
+            .await?;
+
+        Ok(response.setting)
+    }
+
+    async fn flag(&self, setting: Setting) -> SdkResult<bool> {
+        let response = self
+            .sdk
+            .request(GetSetting {
+                setting_id: setting,
+            })
+            .await?;
+
+        response.as_bool().ok_or_else(|| {
+            SdkError::Other(format!(
+                "Setting {:?} is not a boolean: {}",
+                setting, response.setting
+            ))
+        })
+    }
+}
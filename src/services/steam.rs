@@ -0,0 +1,147 @@
+use std::sync::Arc;
+
+use tracing::{debug, warn};
+
+use crate::{
+    protocol::{
+        steam::{
+            SetSteamLocale, SteamAchievementErrorTelemetry, SteamOverlayToStoreFlag,
+            SteamPurchaseConfirmation,
+        },
+        EventBody,
+    },
+    sdk::{OriginSdk, SdkResult},
+};
+
+/// Outcome of unlocking a Steam achievement, reported back as telemetry on failure
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SteamAchievementOutcome {
+    /// The user stats were received from Steam
+    pub valid_stats: bool,
+    /// Setting the achievement succeeded
+    pub set_stat: bool,
+    /// Reading the achievement back succeeded
+    pub get_stat: bool,
+}
+
+impl SteamAchievementOutcome {
+    pub fn is_success(&self) -> bool {
+        self.valid_stats && self.set_stat && self.get_stat
+    }
+}
+
+/// Implemented by the game against its Steam integration
+///
+/// Methods are called from [`SteamIntegration::handle_event`] and should not block.
+pub trait SteamBridge: Send + Sync {
+    /// Unlock an achievement in Steam
+    fn unlock_achievement(&self, achievement_id: &str, points: i32) -> SteamAchievementOutcome;
+
+    /// Open the Steam overlay to the store page of an app
+    fn activate_overlay_to_store(&self, app_id: &str, flag: SteamOverlayToStoreFlag);
+
+    /// Language of the Steam client, e.g. `english`
+    fn language(&self) -> Option<String> {
+        None
+    }
+}
+
+/// Routes the Steam events of a title running through Steam into a [`SteamBridge`]
+///
+/// Unlocks achievements on [`EventBody::SteamAchievementEvent`] and reports failures
+/// with [`SteamAchievementErrorTelemetry`], and opens the Steam overlay store on
+/// [`EventBody::SteamActivateOverlayToStoreEvent`].
+pub struct SteamIntegration<B: SteamBridge> {
+    sdk: Arc<OriginSdk>,
+    bridge: B,
+}
+
+impl<B: SteamBridge> SteamIntegration<B> {
+    /// Create the integration and report the Steam language to the client
+    pub async fn new(sdk: Arc<OriginSdk>, bridge: B) -> SdkResult<Self> {
+        let integration = Self { sdk, bridge };
+
+        if let Some(language) = integration.bridge.language() {
+            integration.set_locale(language).await?;
+        }
+
+        Ok(integration)
+    }
+
+    pub fn bridge(&self) -> &B {
+        &self.bridge
+    }
+
+    /// Report the language of the Steam client
+    pub async fn set_locale(&self, language: impl Into<String>) -> SdkResult<()> {
+        self.sdk
+            .request(SetSteamLocale {
+                language: language.into(),
+            })
+            .await?;
+
+        Ok(())
+    }
+
+    /// Forward the result of a Steam microtransaction authorization
+    pub async fn confirm_purchase(
+        &self,
+        app_id: i32,
+        order_id: u64,
+        authorized: bool,
+    ) -> SdkResult<()> {
+        self.sdk
+            .request(SteamPurchaseConfirmation {
+                app_id,
+                order_id,
+                authorized,
+            })
+            .await?;
+
+        Ok(())
+    }
+
+    /// Feed an event received from the server into the bridge
+    pub async fn handle_event(&self, event: &EventBody) {
+        match event {
+            EventBody::SteamAchievementEvent(event) => {
+                let outcome = self
+                    .bridge
+                    .unlock_achievement(&event.achievement_id, event.points);
+
+                if outcome.is_success() {
+                    debug!("Unlocked Steam achievement {}", event.achievement_id);
+                    return;
+                }
+
+                warn!(
+                    "Failed to unlock Steam achievement {}: {:?}",
+                    event.achievement_id, outcome
+                );
+
+                let result = self
+                    .sdk
+                    .request(SteamAchievementErrorTelemetry {
+                        valid_stats: outcome.valid_stats,
+                        set_stat: outcome.set_stat,
+                        get_stat: outcome.get_stat,
+                    })
+                    .await;
+
+                if let Err(err) = result {
+                    warn!("Failed to send Steam achievement telemetry: {}", err);
+                }
+            }
+            EventBody::SteamActivateOverlayToStoreEvent(event) => {
+                debug!(
+                    "Opening Steam store for app {} (offer {})",
+                    event.app_id, event.offer_id
+                );
+
+                self.bridge
+                    .activate_overlay_to_store(&event.app_id, event.flag);
+            }
+            _ => {}
+        }
+    }
+}
@@ -0,0 +1,652 @@
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+    time::Duration,
+};
+
+use tokio::{
+    sync::{broadcast, broadcast::error::RecvError, Mutex},
+    time::Instant,
+};
+use tracing::{debug, warn};
+
+use crate::{
+    protocol::{
+        commerce::{
+            Category, Checkout, GetCatalog, GetStore, Offer, QueryCategories, QueryOffers,
+            SelectStore, Store,
+        },
+        entitlements::{Entitlement, QueryManifest, RefreshEntitlements},
+        EventBody,
+    },
+    sdk::{OriginSdk, SdkError, SdkResult},
+    services::current_user_ids,
+};
+
+/// A category of the catalog with links to its parent, children and offers
+#[derive(Debug, Clone)]
+pub struct CategoryNode {
+    pub category_id: String,
+    pub parent_id: Option<String>,
+    pub name: String,
+    pub description: String,
+    pub image_id: String,
+    pub most_popular: i32,
+    pub children: Vec<String>,
+    pub offers: Vec<String>,
+}
+
+/// Criteria for selecting offers, `None` fields match every offer
+#[derive(Debug, Clone, Default)]
+pub struct OfferFilter {
+    pub owned: Option<bool>,
+    pub purchasable: Option<bool>,
+    pub discounted: Option<bool>,
+    pub include_hidden: bool,
+}
+
+impl OfferFilter {
+    /// Returns true if the offer satisfies every criterion
+    pub fn matches(&self, offer: &Offer) -> bool {
+        (self.include_hidden || !offer.b_hidden)
+            && self.owned.is_none_or(|owned| offer.b_is_owned == owned)
+            && self
+                .purchasable
+                .is_none_or(|purchasable| offer.b_can_purchase == purchasable)
+            && self
+                .discounted
+                .is_none_or(|discounted| offer.b_is_discounted == discounted)
+    }
+}
+
+/// Indexed view of nested catalog categories and their offers
+#[derive(Debug, Clone, Default)]
+pub struct CategoryTree {
+    nodes: HashMap<String, CategoryNode>,
+    roots: Vec<String>,
+    offers: HashMap<String, Offer>,
+}
+
+impl CategoryTree {
+    /// Build the index from categories in any order
+    ///
+    /// Categories are linked once all of them are known, so a child may be listed
+    /// before its parent. Categories whose parent is unknown are treated as roots.
+    pub fn new(categories: &[Category]) -> Self {
+        let mut tree = Self::default();
+        let mut order = Vec::new();
+
+        for category in categories {
+            tree.collect(category, None, &mut order);
+        }
+
+        for category_id in order {
+            let parent_id = tree.nodes[&category_id]
+                .parent_id
+                .clone()
+                .filter(|parent_id| {
+                    *parent_id != category_id && tree.nodes.contains_key(parent_id)
+                });
+
+            match &parent_id {
+                Some(parent_id) => {
+                    if let Some(parent) = tree.nodes.get_mut(parent_id) {
+                        if !parent.children.contains(&category_id) {
+                            parent.children.push(category_id.clone());
+                        }
+                    }
+                }
+                None => {
+                    if !tree.roots.contains(&category_id) {
+                        tree.roots.push(category_id.clone());
+                    }
+                }
+            }
+
+            if let Some(node) = tree.nodes.get_mut(&category_id) {
+                node.parent_id = parent_id;
+            }
+        }
+
+        tree
+    }
+
+    fn collect(&mut self, category: &Category, enclosing: Option<&str>, order: &mut Vec<String>) {
+        // The server usually sends the parent id, fall back to the nesting otherwise
+        let parent_id = if category.parent_id.is_empty() {
+            enclosing.map(str::to_string)
+        } else {
+            Some(category.parent_id.clone())
+        };
+
+        let node = CategoryNode {
+            category_id: category.category_id.clone(),
+            parent_id,
+            name: category.name.clone(),
+            description: category.description.clone(),
+            image_id: category.image_id.clone(),
+            most_popular: category.most_popular,
+            children: Vec::new(),
+            offers: category
+                .offers
+                .iter()
+                .map(|offer| offer.offer_id.clone())
+                .collect(),
+        };
+
+        if self
+            .nodes
+            .insert(category.category_id.clone(), node)
+            .is_none()
+        {
+            order.push(category.category_id.clone());
+        }
+
+        for offer in &category.offers {
+            self.offers.insert(offer.offer_id.clone(), offer.clone());
+        }
+
+        for child in &category.categories {
+            self.collect(child, Some(&category.category_id), order);
+        }
+    }
+
+    /// Categories without a parent
+    pub fn roots(&self) -> Vec<&CategoryNode> {
+        self.roots
+            .iter()
+            .filter_map(|id| self.nodes.get(id))
+            .collect()
+    }
+
+    /// Look up a category by id
+    pub fn category(&self, category_id: &str) -> Option<&CategoryNode> {
+        self.nodes.get(category_id)
+    }
+
+    /// Direct children of a category
+    pub fn children(&self, category_id: &str) -> Vec<&CategoryNode> {
+        self.nodes
+            .get(category_id)
+            .map(|node| {
+                node.children
+                    .iter()
+                    .filter_map(|id| self.nodes.get(id))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Categories from the root down to the given category
+    pub fn path(&self, category_id: &str) -> Vec<&CategoryNode> {
+        let mut path = Vec::new();
+        let mut current = self.nodes.get(category_id);
+
+        while let Some(node) = current {
+            // Guard against malformed catalogs where a category is its own ancestor
+            if path.len() > self.nodes.len() {
+                break;
+            }
+
+            path.push(node);
+            current = node.parent_id.as_ref().and_then(|id| self.nodes.get(id));
+        }
+
+        path.reverse();
+        path
+    }
+
+    /// Look up an offer by id
+    pub fn offer(&self, offer_id: &str) -> Option<&Offer> {
+        self.offers.get(offer_id)
+    }
+
+    /// Offers of a category and all of its descendants matching the filter
+    pub fn offers_in(&self, category_id: &str, filter: &OfferFilter) -> Vec<&Offer> {
+        let mut offers = Vec::new();
+        let mut pending = vec![category_id];
+
+        while let Some(id) = pending.pop() {
+            let Some(node) = self.nodes.get(id) else {
+                continue;
+            };
+
+            offers.extend(
+                node.offers
+                    .iter()
+                    .filter_map(|offer_id| self.offers.get(offer_id))
+                    .filter(|offer| filter.matches(offer)),
+            );
+            pending.extend(node.children.iter().map(String::as_str));
+        }
+
+        offers
+    }
+
+    /// Every known offer matching the filter
+    pub fn offers(&self, filter: &OfferFilter) -> Vec<&Offer> {
+        self.offers
+            .values()
+            .filter(|offer| filter.matches(offer))
+            .collect()
+    }
+
+    fn update_offers(&mut self, offers: Vec<Offer>) {
+        for offer in offers {
+            self.offers.insert(offer.offer_id.clone(), offer);
+        }
+    }
+}
+
+/// In-game storefront of the current user
+///
+/// Loads stores and catalogs into a [`CategoryTree`] and runs checkouts that wait
+/// for the [`EventBody::PurchaseEvent`] of the checked out offers before refreshing
+/// entitlements.
+pub struct Storefront {
+    sdk: Arc<OriginSdk>,
+    user_id: u64,
+    stores: Mutex<Vec<Store>>,
+    tree: Mutex<CategoryTree>,
+    purchases: broadcast::Sender<String>,
+}
+
+impl Storefront {
+    /// Create an empty storefront for the current user
+    pub async fn new(sdk: Arc<OriginSdk>) -> SdkResult<Self> {
+        let (user_id, _) = current_user_ids(&sdk).await?;
+        let (purchases, _) = broadcast::channel(16);
+
+        Ok(Self {
+            sdk,
+            user_id,
+            stores: Mutex::new(Vec::new()),
+            tree: Mutex::new(CategoryTree::default()),
+            purchases,
+        })
+    }
+
+    /// Load a store and its catalogs, replacing the category tree
+    pub async fn load(&self, store_id: u64) -> SdkResult<()> {
+        let stores = self
+            .sdk
+            .request(GetStore {
+                user_id: self.user_id,
+                store_id,
+            })
+            .await?
+            .stores;
+
+        let catalogs = self
+            .sdk
+            .request(GetCatalog {
+                user_id: self.user_id,
+            })
+            .await?
+            .catalogs;
+
+        // Catalogs embedded in the store and the ones returned separately are merged
+        let categories: Vec<Category> = stores
+            .iter()
+            .flat_map(|store| store.catalogs.iter())
+            .chain(catalogs.iter())
+            .flat_map(|catalog| catalog.categories.iter().cloned())
+            .collect();
+
+        debug!("Loaded {} top level store categories", categories.len());
+
+        *self.tree.lock().await = CategoryTree::new(&categories);
+        *self.stores.lock().await = stores;
+        Ok(())
+    }
+
+    /// Load only the given categories, or all of them when `filter` is empty
+    pub async fn load_categories(&self, filter: Vec<String>) -> SdkResult<()> {
+        let categories = self
+            .sdk
+            .request(QueryCategories {
+                user_id: self.user_id,
+                filter_categories: filter,
+            })
+            .await?
+            .categories;
+
+        *self.tree.lock().await = CategoryTree::new(&categories);
+        Ok(())
+    }
+
+    /// Query fresh data for the given offers and update the tree
+    pub async fn refresh_offers(&self, offers: Vec<String>) -> SdkResult<()> {
+        let offers = self
+            .sdk
+            .request(QueryOffers {
+                user_id: self.user_id,
+                filter_categories: Vec::new(),
+                filter_master_title_ids: Vec::new(),
+                filter_offers: offers,
+            })
+            .await?
+            .offers;
+
+        self.tree.lock().await.update_offers(offers);
+        Ok(())
+    }
+
+    /// Select the store used for checkouts
+    pub async fn select_store(&self, store: SelectStore) -> SdkResult<()> {
+        self.sdk.request(store).await?;
+        Ok(())
+    }
+
+    /// Stores returned by the last [`Storefront::load`]
+    pub async fn stores(&self) -> Vec<Store> {
+        self.stores.lock().await.clone()
+    }
+
+    /// Snapshot of the category tree
+    pub async fn tree(&self) -> CategoryTree {
+        self.tree.lock().await.clone()
+    }
+
+    /// Every known offer matching the filter
+    pub async fn offers(&self, filter: &OfferFilter) -> Vec<Offer> {
+        self.tree
+            .lock()
+            .await
+            .offers(filter)
+            .into_iter()
+            .cloned()
+            .collect()
+    }
+
+    /// Purchase offers and wait until the purchase completes
+    ///
+    /// Returns the entitlements granted by the purchase. Purchase events are matched
+    /// against the checked out offers, other purchases completing meanwhile are
+    /// ignored. Fails if no matching [`EventBody::PurchaseEvent`] is received within
+    /// `timeout`, e.g. because the user closed the checkout dialog.
+    pub async fn checkout(
+        &self,
+        offers: Vec<String>,
+        timeout: Duration,
+    ) -> SdkResult<Vec<Entitlement>> {
+        let deadline = Instant::now() + timeout;
+
+        // Fresh offer data tells which items the purchase grants and what is owned
+        if let Err(err) = self.refresh_offers(offers.clone()).await {
+            warn!("Failed to refresh offers before checkout: {}", err);
+        }
+
+        let currency = self.currency_of(&offers).await;
+        let (items, owned) = {
+            let tree = self.tree.lock().await;
+            let offers: Vec<&Offer> = offers.iter().filter_map(|id| tree.offer(id)).collect();
+            (granted_items(&offers), owned_offers(&offers))
+        };
+
+        // Subscribe before sending, the event may arrive before the response
+        let mut purchases = self.purchases.subscribe();
+
+        self.sdk
+            .request(Checkout {
+                user_id: self.user_id,
+                currency,
+                offers: offers.clone(),
+            })
+            .await?;
+
+        loop {
+            let manifest = match tokio::time::timeout_at(deadline, purchases.recv()).await {
+                Ok(Ok(manifest)) => manifest,
+                Ok(Err(RecvError::Lagged(_))) => continue,
+                Ok(Err(RecvError::Closed)) => {
+                    return Err(SdkError::Other("Purchase channel closed".to_string()))
+                }
+                Err(_) => return Err(SdkError::Other("Checkout timed out".to_string())),
+            };
+
+            let granted = self
+                .sdk
+                .request(QueryManifest {
+                    user_id: self.user_id,
+                    manifest,
+                })
+                .await?
+                .entitlements;
+
+            let matched = if items.is_empty() {
+                // The offers do not list their entitlements, check which became owned
+                self.refresh_offers(offers.clone()).await?;
+
+                let tree = self.tree.lock().await;
+                offers
+                    .iter()
+                    .filter_map(|id| tree.offer(id))
+                    .any(|offer| offer.b_is_owned && !owned.contains(&offer.offer_id))
+            } else {
+                is_purchase_of(&items, &granted)
+            };
+
+            if !matched {
+                debug!("Ignoring purchase unrelated to the checkout");
+                continue;
+            }
+
+            self.sdk.request(RefreshEntitlements).await?;
+
+            if let Err(err) = self.refresh_offers(offers).await {
+                warn!("Failed to refresh purchased offers: {}", err);
+            }
+
+            return Ok(granted);
+        }
+    }
+
+    /// Feed an event received from the server into the storefront
+    pub async fn handle_event(&self, event: &EventBody) {
+        if let EventBody::PurchaseEvent(event) = event {
+            debug!("Purchase completed");
+            let _ = self.purchases.send(event.manifest.clone());
+        }
+    }
+
+    async fn currency_of(&self, offers: &[String]) -> String {
+        let tree = self.tree.lock().await;
+        let currency = offers
+            .iter()
+            .filter_map(|id| tree.offer(id))
+            .map(|offer| offer.currency.clone())
+            .find(|currency| !currency.is_empty());
+
+        match currency {
+            Some(currency) => currency,
+            None => self
+                .stores
+                .lock()
+                .await
+                .first()
+                .map(|store| store.default_currency.clone())
+                .unwrap_or_default(),
+        }
+    }
+}
+
+/// Item ids of the entitlements granted by the offers
+fn granted_items(offers: &[&Offer]) -> HashSet<String> {
+    offers
+        .iter()
+        .flat_map(|offer| offer.entitlements.iter())
+        .map(|entitlement| entitlement.item_id.clone())
+        .filter(|item_id| !item_id.is_empty())
+        .collect()
+}
+
+/// Ids of the offers already owned
+fn owned_offers(offers: &[&Offer]) -> HashSet<String> {
+    offers
+        .iter()
+        .filter(|offer| offer.b_is_owned)
+        .map(|offer| offer.offer_id.clone())
+        .collect()
+}
+
+/// Returns true if a purchase granted any of the expected items
+fn is_purchase_of(items: &HashSet<String>, granted: &[Entitlement]) -> bool {
+    granted
+        .iter()
+        .any(|entitlement| items.contains(&entitlement.item_id))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn offer(offer_id: &str, owned: bool, discounted: bool) -> Offer {
+        Offer {
+            r#type: String::new(),
+            offer_id: offer_id.to_string(),
+            name: offer_id.to_string(),
+            description: String::new(),
+            image_id: String::new(),
+            game_distribution_sub_type: String::new(),
+            b_is_owned: owned,
+            b_hidden: false,
+            b_can_purchase: !owned,
+            purchase_date: String::new(),
+            download_date: String::new(),
+            playable_date: String::new(),
+            use_end_date: String::new(),
+            download_size: 0,
+            currency: "USD".to_string(),
+            b_is_discounted: discounted,
+            price: 7.5,
+            localized_price: String::new(),
+            original_price: 10.0,
+            localized_original_price: "$10.00".to_string(),
+            inventory_cap: 0,
+            inventory_sold: 0,
+            inventory_available: 0,
+            entitlements: Vec::new(),
+        }
+    }
+
+    fn category(id: &str, categories: Vec<Category>, offers: Vec<Offer>) -> Category {
+        Category {
+            r#type: String::new(),
+            category_id: id.to_string(),
+            parent_id: String::new(),
+            name: id.to_string(),
+            description: String::new(),
+            most_popular: 0,
+            image_id: String::new(),
+            categories,
+            offers,
+        }
+    }
+
+    #[test]
+    fn test_category_tree() {
+        let tree = CategoryTree::new(&[category(
+            "root",
+            vec![
+                category("dlc", vec![], vec![offer("map-pack", true, false)]),
+                category(
+                    "cosmetics",
+                    vec![category("skins", vec![], vec![offer("skin", false, true)])],
+                    vec![],
+                ),
+            ],
+            vec![],
+        )]);
+
+        assert_eq!(tree.roots().len(), 1);
+        assert_eq!(tree.children("root").len(), 2);
+
+        let path: Vec<&str> = tree
+            .path("skins")
+            .iter()
+            .map(|node| node.category_id.as_str())
+            .collect();
+        assert_eq!(path, ["root", "cosmetics", "skins"]);
+
+        let all = OfferFilter::default();
+        assert_eq!(tree.offers_in("root", &all).len(), 2);
+        assert_eq!(tree.offers_in("cosmetics", &all).len(), 1);
+
+        let purchasable = OfferFilter {
+            purchasable: Some(true),
+            discounted: Some(true),
+            ..Default::default()
+        };
+        let offers = tree.offers(&purchasable);
+        assert_eq!(offers.len(), 1);
+        assert_eq!(offers[0].offer_id, "skin");
+
+        assert_eq!(offers[0].display_price(), "7.50 USD");
+        assert_eq!(offers[0].display_original_price(), "$10.00");
+        assert_eq!(offers[0].discount_percent(), 25);
+    }
+
+    #[test]
+    fn test_category_tree_flat_list() {
+        let mut child = category("skins", vec![], vec![offer("skin", false, false)]);
+        child.parent_id = "cosmetics".to_string();
+        let mut parent = category("cosmetics", vec![], vec![]);
+        parent.parent_id = "root".to_string();
+        let mut orphan = category("orphan", vec![], vec![]);
+        orphan.parent_id = "missing".to_string();
+
+        // Children listed before their parents
+        let tree = CategoryTree::new(&[child, parent, category("root", vec![], vec![]), orphan]);
+
+        let roots: Vec<&str> = tree
+            .roots()
+            .iter()
+            .map(|node| node.category_id.as_str())
+            .collect();
+        assert_eq!(roots, ["root", "orphan"]);
+        assert!(tree.category("orphan").unwrap().parent_id.is_none());
+
+        let path: Vec<&str> = tree
+            .path("skins")
+            .iter()
+            .map(|node| node.category_id.as_str())
+            .collect();
+        assert_eq!(path, ["root", "cosmetics", "skins"]);
+        assert_eq!(tree.children("cosmetics").len(), 1);
+        assert_eq!(tree.offers_in("root", &OfferFilter::default()).len(), 1);
+    }
+
+    #[test]
+    fn test_is_purchase_of() {
+        let entitlement = |item_id: &str| Entitlement {
+            type_: String::new(),
+            item_id: item_id.to_string(),
+            entitlement_id: String::new(),
+            entitlement_tag: String::new(),
+            group: String::new(),
+            resource_id: String::new(),
+            use_count: 0,
+            expiration: String::new(),
+            grant_date: String::new(),
+            last_modified_date: String::new(),
+            version: 0,
+        };
+
+        let mut skin = offer("skin", true, false);
+        skin.entitlements = vec![entitlement("skin-item")];
+        let map_pack = offer("map-pack", false, false);
+
+        let items = granted_items(&[&skin, &map_pack]);
+        assert_eq!(items, HashSet::from(["skin-item".to_string()]));
+        assert_eq!(
+            owned_offers(&[&skin, &map_pack]),
+            HashSet::from(["skin".to_string()])
+        );
+
+        assert!(is_purchase_of(&items, &[entitlement("skin-item")]));
+        // Another purchase completing during the checkout
+        assert!(!is_purchase_of(&items, &[entitlement("other-item")]));
+        assert!(!is_purchase_of(&items, &[]));
+    }
+}
@@ -0,0 +1,299 @@
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use tokio::{sync::watch, task::JoinHandle};
+use tracing::{debug, info, warn};
+
+use crate::{
+    protocol::entitlements::{ExtendTrial, ExtendTrialResponse},
+    sdk::{OriginSdk, SdkResult},
+    services::current_user_ids,
+};
+
+/// State of the free trial
+#[derive(Debug, Clone, PartialEq)]
+pub enum TrialStatus {
+    /// The first extension has not completed yet
+    Starting,
+    /// The trial is running and will end at `expires_at` unless extended again
+    Active {
+        remaining: Duration,
+        expires_at: Instant,
+    },
+    /// The trial time is used up, the game is expected to shut down within `shutdown_in`
+    Expired { shutdown_in: Duration },
+}
+
+/// Shortest wait between two extension attempts, whatever the server suggests
+const MIN_RETRY_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Timing hints from the last [`ExtendTrialResponse`]
+#[derive(Debug, Clone, Copy)]
+struct Schedule {
+    retry_count: u32,
+    retry_after_fail: Duration,
+    extend_before_expire: Duration,
+    sleep_before_nuke: Duration,
+}
+
+impl Default for Schedule {
+    fn default() -> Self {
+        Self {
+            retry_count: ExtendTrialResponse::default_retry_count() as u32,
+            retry_after_fail: secs(ExtendTrialResponse::default_retry_after_fail_sec()),
+            extend_before_expire: secs(ExtendTrialResponse::default_extend_before_expire_sec()),
+            sleep_before_nuke: secs(ExtendTrialResponse::default_sleep_before_nuke_sec()),
+        }
+    }
+}
+
+impl From<&ExtendTrialResponse> for Schedule {
+    fn from(response: &ExtendTrialResponse) -> Self {
+        Self {
+            retry_count: response.retry_count.max(0) as u32,
+            retry_after_fail: secs(response.retry_after_fail_sec),
+            extend_before_expire: secs(response.extend_before_expire_sec),
+            sleep_before_nuke: secs(response.sleep_before_nuke_sec),
+        }
+    }
+}
+
+impl Schedule {
+    /// Wait before retrying a failed extension
+    fn retry_delay(&self) -> Duration {
+        self.retry_after_fail.max(MIN_RETRY_INTERVAL)
+    }
+
+    /// Wait before the next extension while `remaining` trial time is left
+    fn next_extension(&self, remaining: Duration) -> Duration {
+        // Extend shortly before the trial runs out. When already inside that window
+        // the server did not grant more time, so only poll at the retry interval.
+        match remaining.checked_sub(self.extend_before_expire) {
+            Some(wait) if !wait.is_zero() => wait,
+            _ => self.retry_delay().min(remaining),
+        }
+    }
+}
+
+fn secs(value: i32) -> Duration {
+    Duration::from_secs(value.max(0) as u64)
+}
+
+/// Background task keeping a free trial alive
+///
+/// Calls [`ExtendTrial`] shortly before the trial runs out, as dictated by the
+/// `ExtendBeforeExpireSec` hint of the previous response, and retries failed
+/// extensions, including responses with a non-zero `Code`, `RetryCount` times every
+/// `RetryAfterFailSec` but no more often than every five seconds. Once the trial
+/// cannot be extended anymore the status switches to [`TrialStatus::Expired`] when
+/// the remaining time is used up. The task is stopped when the keeper is dropped.
+pub struct TrialKeeper {
+    status: watch::Receiver<TrialStatus>,
+    handle: JoinHandle<()>,
+}
+
+impl Drop for TrialKeeper {
+    fn drop(&mut self) {
+        self.handle.abort();
+    }
+}
+
+impl TrialKeeper {
+    /// Start keeping the trial of the current user alive
+    pub async fn start(
+        sdk: Arc<OriginSdk>,
+        request_ticket: impl Into<String>,
+        ticket_engine: i32,
+    ) -> SdkResult<Self> {
+        let (user_id, _) = current_user_ids(&sdk).await?;
+        let (status_tx, status) = watch::channel(TrialStatus::Starting);
+
+        let handle = tokio::spawn(Self::run(
+            sdk,
+            user_id,
+            request_ticket.into(),
+            ticket_engine,
+            status_tx,
+        ));
+
+        Ok(Self { status, handle })
+    }
+
+    /// Current state of the trial
+    pub fn status(&self) -> TrialStatus {
+        self.status.borrow().clone()
+    }
+
+    /// Subscribe to remaining time updates and the final expiration
+    pub fn subscribe(&self) -> watch::Receiver<TrialStatus> {
+        self.status.clone()
+    }
+
+    /// Wait until the trial expires
+    pub async fn expired(&self) -> Duration {
+        let mut status = self.status.clone();
+
+        loop {
+            if let TrialStatus::Expired { shutdown_in } = *status.borrow_and_update() {
+                return shutdown_in;
+            }
+
+            if status.changed().await.is_err() {
+                // The task only stops after reporting the expiration
+                return Duration::ZERO;
+            }
+        }
+    }
+
+    async fn run(
+        sdk: Arc<OriginSdk>,
+        user_id: u64,
+        mut ticket: String,
+        ticket_engine: i32,
+        status: watch::Sender<TrialStatus>,
+    ) {
+        let mut schedule = Schedule::default();
+        let mut expires_at: Option<Instant> = None;
+        let mut failures = 0;
+
+        loop {
+            let result = sdk
+                .request(ExtendTrial {
+                    user_id,
+                    request_ticket: ticket.clone(),
+                    ticket_engine,
+                })
+                .await;
+
+            let response = match result {
+                Ok(response) if response.code == 0 => Ok(response),
+                Ok(response) => {
+                    // The timing hints still apply to the retries
+                    schedule = Schedule::from(&response);
+                    Err(format!("code {}", response.code))
+                }
+                Err(err) => Err(err.to_string()),
+            };
+
+            let response = match response {
+                Ok(response) => response,
+                Err(err) => {
+                    failures += 1;
+                    warn!("Failed to extend trial (attempt {}): {}", failures, err);
+
+                    let out_of_time = expires_at.is_some_and(|at| at <= Instant::now());
+                    if failures > schedule.retry_count || out_of_time {
+                        break;
+                    }
+
+                    tokio::time::sleep(schedule.retry_delay()).await;
+                    continue;
+                }
+            };
+
+            failures = 0;
+            schedule = Schedule::from(&response);
+
+            if !response.response_ticket.is_empty() {
+                ticket = response.response_ticket.clone();
+            }
+
+            let remaining = secs(response.total_time_remaining);
+            let deadline = Instant::now() + remaining;
+            expires_at = Some(deadline);
+
+            debug!(
+                "Trial extended by {}s, {}s remaining",
+                response.time_granted, response.total_time_remaining
+            );
+
+            let _ = status.send(TrialStatus::Active {
+                remaining,
+                expires_at: deadline,
+            });
+
+            if remaining.is_zero() {
+                break;
+            }
+
+            tokio::time::sleep(schedule.next_extension(remaining)).await;
+        }
+
+        if let Some(deadline) = expires_at {
+            tokio::time::sleep_until(deadline.into()).await;
+        }
+
+        info!("Trial expired");
+        let _ = status.send(TrialStatus::Expired {
+            shutdown_in: schedule.sleep_before_nuke,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn schedule(retry_after_fail: u64, extend_before_expire: u64) -> Schedule {
+        Schedule {
+            retry_count: 3,
+            retry_after_fail: Duration::from_secs(retry_after_fail),
+            extend_before_expire: Duration::from_secs(extend_before_expire),
+            sleep_before_nuke: Duration::from_secs(30),
+        }
+    }
+
+    #[test]
+    fn test_next_extension() {
+        let schedule = schedule(30, 60);
+
+        // Extend a minute before the trial runs out
+        assert_eq!(
+            schedule.next_extension(Duration::from_secs(600)),
+            Duration::from_secs(540)
+        );
+        // Inside the window, poll at the retry interval
+        assert_eq!(
+            schedule.next_extension(Duration::from_secs(60)),
+            Duration::from_secs(30)
+        );
+        // But never past the end of the trial
+        assert_eq!(
+            schedule.next_extension(Duration::from_secs(10)),
+            Duration::from_secs(10)
+        );
+    }
+
+    #[test]
+    fn test_minimum_backoff() {
+        let schedule = schedule(0, 60);
+
+        assert_eq!(schedule.retry_delay(), MIN_RETRY_INTERVAL);
+        assert_eq!(
+            schedule.next_extension(Duration::from_secs(60)),
+            MIN_RETRY_INTERVAL
+        );
+    }
+
+    #[test]
+    fn test_schedule_from_response() {
+        let response = ExtendTrialResponse {
+            code: 0,
+            total_time_remaining: 600,
+            time_granted: 300,
+            response_ticket: String::new(),
+            retry_count: -1,
+            retry_after_fail_sec: -5,
+            extend_before_expire_sec: 120,
+            sleep_before_nuke_sec: 15,
+        };
+
+        let schedule = Schedule::from(&response);
+        assert_eq!(schedule.retry_count, 0);
+        assert_eq!(schedule.retry_delay(), MIN_RETRY_INTERVAL);
+        assert_eq!(schedule.extend_before_expire, Duration::from_secs(120));
+        assert_eq!(schedule.sleep_before_nuke, Duration::from_secs(15));
+    }
+}